@@ -1,50 +1,228 @@
+use std::fmt;
 use std::iter::Peekable;
+use std::mem;
 use std::vec::IntoIter;
 
-use crate::token::{Token, TokenKind};
+use crate::token::{Location, Token, TokenKind};
 use crate::tokenizer::LiteralData;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Program {
-    functions: Vec<Function>,
-    structs: Vec<Structure>,
+    pub imports: Vec<ImportPath>,
+    pub functions: Vec<Function>,
+    pub structs: Vec<Structure>,
 }
 
+/// A script of statements outside of any function, for quick experiments and
+/// a REPL.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AbstractSyntaxTree {
-    statements: Vec<Statement>,
+    pub statements: Vec<Statement>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ParseError {
     token: Option<Token>,
-    expected: Vec<TokenKind>,
+    kind: ParseErrorKind,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    Expected(Vec<TokenKind>),
+    /// The left side of an `=` is not something that can be assigned to. The
+    /// token is the `=`.
+    InvalidAssignmentTarget,
+    /// A prefix operator with nothing after it to apply to. The token is the
+    /// operator.
+    MissingOperand,
+    /// A bracket closed by a closing bracket of a different kind.
+    MismatchedBracket {
+        opened: TokenKind,
+        open_location: Location,
+        found: TokenKind,
+    },
+    /// An `=` right after the condition of an `if` or `while`, where `==` was
+    /// probably meant. The token is the `=`.
+    AssignmentInCondition,
+    /// An `else` that doesn't follow the body of an `if`.
+    ElseWithoutIf,
+}
+
+impl ParseErrorKind {
+    /// A short code identifying the kind of error, see `DESIGN.md`. Codes must
+    /// never change or be reused, new kinds get the next free code.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::Expected(_) => "E0101",
+            Self::MismatchedBracket { .. } => "E0102",
+            Self::InvalidAssignmentTarget => "E0103",
+            Self::MissingOperand => "E0104",
+            Self::AssignmentInCondition => "E0105",
+            Self::ElseWithoutIf => "E0106",
+        }
+    }
+}
+
+impl ParseError {
+    pub const fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    pub const fn token(&self) -> Option<&Token> {
+        self.token.as_ref()
+    }
+
+    /// The location of the offending token, which is the `EndOfFile` for
+    /// errors at the end of the input.
+    pub fn location(&self) -> Option<Location> {
+        self.token.as_ref().map(Token::location)
+    }
+
+    pub const fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+}
+
+/// The tokens the parser reads, which end with the `EndOfFile` so that errors
+/// at the end of the input have a location.
 type TokenIter = Peekable<IntoIter<Token>>;
 
+/// The kinds of tokens that can start a top-level item, where parsing resumes
+/// after an error.
+const ITEM_START: [TokenKind; 4] = [
+    TokenKind::Use,
+    TokenKind::Hash,
+    TokenKind::FunctionDefinition,
+    TokenKind::Struct,
+];
+
 impl Program {
-    pub fn from_tokens(tokens: Vec<Token>, literal_data: LiteralData) -> Result<Self, ParseError> {
+    /// Parses a whole program, collecting every error instead of stopping at
+    /// the first one. After an error the rest of the item is skipped up to the
+    /// start of the next top-level item.
+    pub fn from_tokens(
+        tokens: Vec<Token>,
+        literal_data: LiteralData,
+    ) -> Result<Self, Vec<ParseError>> {
+        let mut imports = Vec::new();
         let mut functions = Vec::new();
         let mut structs = Vec::new();
+        // attributes seen since the last definition, which they belong to
+        let mut attributes = Vec::new();
+        let mut errors = Vec::new();
 
         let mut tokens = tokens.into_iter().peekable();
+        // the token an error was found at, if it starts the next item
+        let mut resume_at = None;
 
-        while let Some(token) = tokens.next() {
-            match token.kind() {
+        while let Some(token) = resume_at.take().or_else(|| tokens.next()) {
+            let item_kind = token.kind();
+            let name_location = tokens.peek().map(Token::location);
+            let result = match token.kind() {
+                // with attributes left over this is an error below
+                TokenKind::EndOfFile if attributes.is_empty() => break,
+                TokenKind::Use if attributes.is_empty() => {
+                    parse_import(&mut tokens, &literal_data).map(|import| imports.push(import))
+                }
+                TokenKind::Hash => parse_attribute(&mut tokens, &literal_data)
+                    .map(|attribute| attributes.push(attribute)),
                 TokenKind::FunctionDefinition => {
-                    functions.push(parse_function(&mut tokens, &literal_data)?);
+                    parse_function(&mut tokens, &literal_data).map(|mut function| {
+                        function.attributes = mem::take(&mut attributes);
+                        functions.push(function);
+                    })
                 }
-                TokenKind::Struct => structs.push(parse_struct(&mut tokens, &literal_data)?),
-                _ => {
-                    err_expected(
-                        Some(token),
-                        &[TokenKind::FunctionDefinition, TokenKind::Struct],
-                    )?;
+                TokenKind::Struct => {
+                    parse_struct(&mut tokens, &literal_data).map(|mut structure| {
+                        structure.attributes = mem::take(&mut attributes);
+                        structs.push(structure);
+                    })
+                }
+                // input that failed to tokenize, which was reported already
+                TokenKind::Error => Ok(()),
+                _ => err_expected(
+                    Some(token),
+                    &[TokenKind::FunctionDefinition, TokenKind::Struct],
+                )
+                .map(|_| ()),
+            };
+
+            if let Err(error) = result {
+                // the token of an error is after the start of the failed item,
+                // except for a `use` after attributes, which parses as an
+                // import once the attributes are cleared, so resuming at it
+                // can't loop
+                resume_at = error
+                    .token()
+                    .filter(|token| ITEM_START.contains(&token.kind()))
+                    // a keyword where the name of a definition goes is a
+                    // misused name, like in `fn fn() {}`
+                    .filter(|token| {
+                        !(matches!(item_kind, TokenKind::FunctionDefinition | TokenKind::Struct)
+                            && token.kind().is_keyword()
+                            && Some(token.location()) == name_location)
+                    })
+                    .cloned();
+                errors.push(error);
+                attributes.clear();
+                if resume_at.is_none() {
+                    while tokens
+                        .next_if(|token| {
+                            !ITEM_START.contains(&token.kind())
+                                && token.kind() != TokenKind::EndOfFile
+                        })
+                        .is_some()
+                    {}
+                }
+            }
+        }
+
+        if !attributes.is_empty() {
+            errors.push(ParseError {
+                token: None,
+                kind: ParseErrorKind::Expected(vec![
+                    TokenKind::FunctionDefinition,
+                    TokenKind::Struct,
+                ]),
+            });
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(Self {
+            imports,
+            functions,
+            structs,
+        })
+    }
+}
+
+impl AbstractSyntaxTree {
+    pub fn from_tokens(tokens: Vec<Token>, literal_data: LiteralData) -> Result<Self, ParseError> {
+        let mut statements = Vec::new();
+        let mut tokens = tokens.into_iter().peekable();
+
+        loop {
+            match tokens.peek().map(Token::kind) {
+                Some(TokenKind::EndOfFile) | None => break,
+                Some(TokenKind::EndOfStatement) => {
+                    tokens.next();
                 }
+                Some(_) => match parse_statement(&mut tokens, &literal_data)? {
+                    BlockItem::Statement(statement) => statements.push(statement),
+                    // only there before a `}`, which has nothing to close here
+                    BlockItem::Value(_) => {
+                        err_expected(tokens.next(), &[TokenKind::EndOfStatement])?
+                    }
+                },
             }
         }
 
-        Ok(Self { functions, structs })
+        Ok(Self { statements })
     }
 }
 
@@ -52,153 +230,3396 @@ fn parse_function(
     tokens: &mut TokenIter,
     literal_data: &LiteralData,
 ) -> Result<Function, ParseError> {
-    let name = expect_identifier(tokens, literal_data)?.clone();
+    let (name, location) = expect_identifier_with_location(tokens, literal_data)?;
+    let type_params = parse_type_params(tokens, literal_data)?;
 
-    expect_token(tokens, TokenKind::OpenParentheses)?;
-    let arguments = parse_value_type_list(tokens, literal_data, TokenKind::CloseParentheses)?;
+    let open = expect_token(tokens, TokenKind::OpenParentheses)?;
+    let item_start = [TokenKind::Mutable, TokenKind::Identifier];
+    let arguments = parse_bracketed_list(tokens, &open, &item_start, |tokens| {
+        let mutable = tokens
+            .next_if(|token| token.kind() == TokenKind::Mutable)
+            .is_some();
+        let (name, argument_type) = parse_value_type(tokens, literal_data)?;
+        Ok(Argument {
+            name,
+            mutable,
+            argument_type,
+        })
+    })?;
 
-    let next_token = tokens.next();
-    let return_type = match next_token.as_ref().map(Token::kind) {
-        Some(TokenKind::OpenBraces) => None,
-        Some(TokenKind::RightArrow) => {
-            let type_name = expect_identifier(tokens, literal_data)?;
-            expect_token(tokens, TokenKind::OpenBraces)?;
-            Some(type_name.clone())
-        }
-        _ => err_expected(next_token, &[TokenKind::RightArrow, TokenKind::OpenBraces])?,
-    };
+    let return_type = parse_return_type(tokens, literal_data)?;
 
-    let body = parse_code_block(tokens, literal_data)?;
+    let open = expect_token(tokens, TokenKind::OpenBraces)?;
+    let body = parse_code_block(tokens, literal_data, &open)?;
 
     Ok(Function {
-        name,
+        name: name.clone(),
+        location,
+        attributes: Vec::new(),
+        type_params,
         arguments,
         return_type,
         body,
     })
 }
 
+/// Parses an optional `-> Type` annotation, which must be followed by the `{`
+/// that opens the function body. Without one the function returns `()`.
+fn parse_return_type(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+) -> Result<Type, ParseError> {
+    match tokens.peek().map(Token::kind) {
+        Some(TokenKind::OpenBraces) => Ok(Type::Unit),
+        Some(TokenKind::RightArrow) => {
+            tokens.next();
+            parse_type(tokens, literal_data)
+        }
+        _ => err_expected(
+            tokens.next(),
+            &[TokenKind::RightArrow, TokenKind::OpenBraces],
+        )?,
+    }
+}
+
+/// Parses the `a::b::c;`, `a::*;` or `a::{b, c::d};` of an import after its
+/// `use`.
+fn parse_import(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+) -> Result<ImportPath, ParseError> {
+    let (first, location) = expect_identifier_with_location(tokens, literal_data)?;
+    let mut segments = vec![first.clone()];
+
+    let import = loop {
+        let next_token = tokens.next();
+        match next_token.as_ref().map(Token::kind) {
+            Some(TokenKind::PathSep) => (),
+            Some(TokenKind::EndOfStatement) => {
+                return Ok(ImportPath::Single { segments, location });
+            }
+            _ => err_expected(next_token, &[TokenKind::PathSep, TokenKind::EndOfStatement])?,
+        }
+
+        match tokens.peek().map(Token::kind) {
+            Some(TokenKind::Identifier) => {
+                segments.push(expect_identifier(tokens, literal_data)?.clone());
+            }
+            Some(TokenKind::Mul) => {
+                tokens.next();
+                break ImportPath::Glob {
+                    prefix: segments,
+                    location,
+                };
+            }
+            Some(TokenKind::OpenBraces) => {
+                let open = tokens.next().unwrap();
+                let members =
+                    parse_bracketed_list(tokens, &open, &[TokenKind::Identifier], |tokens| {
+                        parse_import_member(tokens, literal_data)
+                    })?;
+                break ImportPath::Group {
+                    prefix: segments,
+                    members,
+                    location,
+                };
+            }
+            _ => err_expected(
+                tokens.next(),
+                &[TokenKind::Identifier, TokenKind::Mul, TokenKind::OpenBraces],
+            )?,
+        }
+    };
+
+    // a glob or a group must be the last segment
+    expect_token(tokens, TokenKind::EndOfStatement)?;
+    Ok(import)
+}
+
+/// Parses a `b` or `c::d` inside the braces of an import group.
+fn parse_import_member(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+) -> Result<Vec<String>, ParseError> {
+    let mut segments = vec![expect_identifier(tokens, literal_data)?.clone()];
+    while tokens
+        .next_if(|token| token.kind() == TokenKind::PathSep)
+        .is_some()
+    {
+        segments.push(expect_identifier(tokens, literal_data)?.clone());
+    }
+    Ok(segments)
+}
+
+/// Parses the `[name(args)]` of an attribute after its `#`. The argument list
+/// is optional.
+fn parse_attribute(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+) -> Result<Attribute, ParseError> {
+    let open = expect_token(tokens, TokenKind::OpenBrackets)?;
+    let (name, location) = expect_identifier_with_location(tokens, literal_data)?;
+
+    let args = match tokens.peek().map(Token::kind) {
+        Some(TokenKind::OpenParentheses) => {
+            let open = tokens.next().unwrap();
+            parse_expression_list(tokens, literal_data, &open)?
+        }
+        _ => Vec::new(),
+    };
+
+    expect_closing_bracket(tokens, &open)?;
+
+    Ok(Attribute {
+        name: name.clone(),
+        location,
+        args,
+    })
+}
+
 fn parse_struct(
     tokens: &mut TokenIter,
     literal_data: &LiteralData,
 ) -> Result<Structure, ParseError> {
-    let struct_name = expect_identifier(tokens, literal_data)?;
+    let (struct_name, location) = expect_identifier_with_location(tokens, literal_data)?;
+    let type_params = parse_type_params(tokens, literal_data)?;
 
-    expect_token(tokens, TokenKind::OpenBraces)?;
-    let fields = parse_value_type_list(tokens, literal_data, TokenKind::CloseBraces)?;
+    let open = expect_token(tokens, TokenKind::OpenBraces)?;
+    let fields = parse_bracketed_list(tokens, &open, &[TokenKind::Identifier], |tokens| {
+        parse_value_type(tokens, literal_data)
+    })?;
 
     Ok(Structure {
         name: struct_name.clone(),
+        location,
+        attributes: Vec::new(),
+        type_params,
         fields,
     })
 }
 
-fn parse_value_type_list(
+/// Parses an optional `<T, U>` list of type parameter names after the name of
+/// a definition.
+fn parse_type_params(
     tokens: &mut TokenIter,
     literal_data: &LiteralData,
-    end_token: TokenKind,
-) -> Result<Vec<(String, String)>, ParseError> {
+) -> Result<Vec<String>, ParseError> {
+    let mut type_params = Vec::new();
+    if tokens
+        .next_if(|token| token.kind() == TokenKind::Less)
+        .is_none()
+    {
+        return Ok(type_params);
+    }
+
+    // `<` and `>` aren't brackets in expressions, so `parse_bracketed_list`
+    // can't be used
+    loop {
+        match tokens.peek().map(Token::kind) {
+            Some(TokenKind::Greater) => {
+                tokens.next();
+                break;
+            }
+            _ => type_params.push(expect_identifier(tokens, literal_data)?.clone()),
+        }
+
+        let next_token = tokens.next();
+        match next_token.as_ref().map(Token::kind) {
+            Some(TokenKind::Comma) => (),
+            Some(TokenKind::Greater) => break,
+            _ => err_expected(next_token, &[TokenKind::Comma, TokenKind::Greater])?,
+        }
+    }
+
+    Ok(type_params)
+}
+
+/// Parses items separated by commas up to and including the bracket closing
+/// `open`, allowing a trailing comma. `item_start` lists the kinds of tokens
+/// an item can start with.
+fn parse_bracketed_list<T>(
+    tokens: &mut TokenIter,
+    open: &Token,
+    item_start: &[TokenKind],
+    mut parse_item: impl FnMut(&mut TokenIter) -> Result<T, ParseError>,
+) -> Result<Vec<T>, ParseError> {
+    let end_token = closing_bracket(open.kind());
     let mut list = Vec::new();
 
     match tokens.peek().map(Token::kind) {
-        Some(TokenKind::Identifier) => (),
+        Some(kind) if item_start.contains(&kind) => (),
         Some(kind) if kind == end_token => {
             tokens.next();
             return Ok(list);
         }
-        _ => err_expected(tokens.next(), &[TokenKind::Identifier, end_token])?,
+        _ => {
+            let expected = [item_start, &[end_token]].concat();
+            err_expected_in_brackets(tokens.next(), &expected, open)?
+        }
+    }
+
+    loop {
+        list.push(parse_item(tokens)?);
+
+        let next_token = tokens.next();
+        match next_token.as_ref().map(Token::kind) {
+            Some(TokenKind::Comma) => {
+                if tokens.next_if(|token| token.kind() == end_token).is_some() {
+                    break;
+                }
+            }
+            Some(kind) if kind == end_token => break,
+            _ => err_expected_in_brackets(next_token, &[TokenKind::Comma, end_token], open)?,
+        }
+    }
+
+    Ok(list)
+}
+
+/// Parses a single `name: Type` pair.
+fn parse_value_type(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+) -> Result<(String, Type), ParseError> {
+    let value_name = expect_identifier(tokens, literal_data)?;
+    expect_token(tokens, TokenKind::FieldTypeSeparator)?;
+    let value_type = parse_type(tokens, literal_data)?;
+    Ok((value_name.clone(), value_type))
+}
+
+fn parse_type(tokens: &mut TokenIter, literal_data: &LiteralData) -> Result<Type, ParseError> {
+    match tokens.peek().map(Token::kind) {
+        Some(TokenKind::Identifier) => {
+            let name = expect_identifier(tokens, literal_data)?.clone();
+            let args = match tokens.peek().map(Token::kind) {
+                Some(TokenKind::OpenParentheses) => {
+                    let open = tokens.next().unwrap();
+                    parse_type_list(tokens, literal_data, &open)?
+                }
+                _ => Vec::new(),
+            };
+            Ok(Type::Named { name, args })
+        }
+        Some(TokenKind::And) => {
+            tokens.next();
+            Ok(Type::Reference(Box::new(parse_type(tokens, literal_data)?)))
+        }
+        // `&&T`, which is tokenized as a single `&&`
+        Some(TokenKind::LogicalAnd) => {
+            tokens.next();
+            let inner = Type::Reference(Box::new(parse_type(tokens, literal_data)?));
+            Ok(Type::Reference(Box::new(inner)))
+        }
+        // like in expressions, `(T)` is just `T` and a tuple of one type
+        // needs a comma, `(T,)`
+        Some(TokenKind::OpenParentheses) => {
+            let open = tokens.next().unwrap();
+            if tokens
+                .next_if(|token| token.kind() == TokenKind::CloseParentheses)
+                .is_some()
+            {
+                return Ok(Type::Unit);
+            }
+            let first = parse_type(tokens, literal_data)?;
+            let next_token = tokens.next();
+            match next_token.as_ref().map(Token::kind) {
+                Some(TokenKind::CloseParentheses) => Ok(first),
+                Some(TokenKind::Comma) => {
+                    let mut types = vec![first];
+                    types.extend(parse_type_list(tokens, literal_data, &open)?);
+                    Ok(Type::Tuple(types))
+                }
+                _ => err_expected_in_brackets(
+                    next_token,
+                    &[TokenKind::Comma, TokenKind::CloseParentheses],
+                    &open,
+                )?,
+            }
+        }
+        Some(TokenKind::OpenBrackets) => {
+            let open = tokens.next().unwrap();
+            let element_type = parse_type(tokens, literal_data)?;
+            expect_closing_bracket(tokens, &open)?;
+            Ok(Type::Array(Box::new(element_type)))
+        }
+        _ => err_expected(
+            tokens.next(),
+            &[
+                TokenKind::Identifier,
+                TokenKind::And,
+                TokenKind::OpenParentheses,
+                TokenKind::OpenBrackets,
+            ],
+        )?,
+    }
+}
+
+/// Parses types separated by commas up to and including the bracket closing
+/// `open`, allowing a trailing comma.
+fn parse_type_list(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+    open: &Token,
+) -> Result<Vec<Type>, ParseError> {
+    let end_token = closing_bracket(open.kind());
+    let mut list = Vec::new();
+
+    if tokens.peek().map(Token::kind) == Some(end_token) {
+        tokens.next();
+        return Ok(list);
     }
 
     loop {
-        let value_name = expect_identifier(tokens, literal_data)?;
-        expect_token(tokens, TokenKind::FieldTypeSeparator)?;
-        let value_type = expect_identifier(tokens, literal_data)?;
-        list.push((value_name.clone(), value_type.clone()));
+        list.push(parse_type(tokens, literal_data)?);
 
         let next_token = tokens.next();
         match next_token.as_ref().map(Token::kind) {
-            Some(TokenKind::Comma) => continue,
+            Some(TokenKind::Comma) => {
+                if tokens.next_if(|token| token.kind() == end_token).is_some() {
+                    break;
+                }
+            }
             Some(kind) if kind == end_token => break,
-            _ => err_expected(next_token, &[TokenKind::Comma, end_token])?,
+            _ => err_expected_in_brackets(next_token, &[TokenKind::Comma, end_token], open)?,
         }
     }
 
     Ok(list)
 }
 
+/// Parses the statements of a block up to and including the closing `}`. The
+/// opening `{`, `open`, must already have been consumed.
 fn parse_code_block(
     tokens: &mut TokenIter,
     literal_data: &LiteralData,
+    open: &Token,
 ) -> Result<CodeBlock, ParseError> {
-    todo!()
+    let mut statements = Vec::new();
+    let mut value = None;
+
+    loop {
+        match tokens.peek().map(Token::kind) {
+            Some(TokenKind::CloseBraces) => {
+                tokens.next();
+                break;
+            }
+            Some(kind) if is_closing_bracket(kind) => {
+                err_expected_in_brackets(tokens.next(), &[TokenKind::CloseBraces], open)?
+            }
+            // empty statements, which also allows a `;` after a block like
+            // `if a {};`
+            Some(TokenKind::EndOfStatement) => {
+                tokens.next();
+            }
+            Some(TokenKind::EndOfFile) | None => {
+                err_expected(tokens.next(), &[TokenKind::CloseBraces])?
+            }
+            Some(_) => match parse_statement(tokens, literal_data)? {
+                BlockItem::Statement(statement) => statements.push(statement),
+                // always followed by the closing `}`
+                BlockItem::Value(expression) => value = Some(Box::new(expression)),
+            },
+        }
+    }
+
+    Ok(CodeBlock { statements, value })
+}
+
+/// What `parse_statement` found.
+enum BlockItem {
+    Statement(Statement),
+    /// An expression without a `;` at the end of a block, which is the value
+    /// of the block.
+    Value(Expression),
 }
 
 fn parse_statement(
     tokens: &mut TokenIter,
     literal_data: &LiteralData,
-) -> Result<Statement, ParseError> {
-    todo!()
+) -> Result<BlockItem, ParseError> {
+    let statement = match tokens.peek().map(Token::kind) {
+        Some(TokenKind::Label) => {
+            let label = expect_label(tokens, literal_data)?.clone();
+            expect_token(tokens, TokenKind::FieldTypeSeparator)?;
+            expect_token(tokens, TokenKind::While)?;
+            parse_while(tokens, literal_data, Some(label))?
+        }
+        Some(TokenKind::While) => {
+            tokens.next();
+            parse_while(tokens, literal_data, None)?
+        }
+        Some(TokenKind::Else) => {
+            return Err(ParseError {
+                token: tokens.next(),
+                kind: ParseErrorKind::ElseWithoutIf,
+            })
+        }
+        // block-like expressions end at their `}`, so they don't need a `;`
+        // and can't be continued with an operator like other expressions, but
+        // at the end of a block they are its value all the same
+        Some(TokenKind::If | TokenKind::Match | TokenKind::OpenBraces) => {
+            let expression = parse_primary_expression(tokens, literal_data, true)?;
+            if tokens.peek().map(Token::kind) == Some(TokenKind::CloseBraces) {
+                return Ok(BlockItem::Value(expression));
+            }
+            Statement::Expression(expression)
+        }
+        Some(kind @ (TokenKind::Break | TokenKind::Continue)) => {
+            tokens.next();
+            let label = match tokens.peek().map(Token::kind) {
+                Some(TokenKind::Label) => Some(expect_label(tokens, literal_data)?.clone()),
+                _ => None,
+            };
+            expect_token(tokens, TokenKind::EndOfStatement)?;
+            match kind {
+                TokenKind::Break => Statement::Break { label },
+                _ => Statement::Continue { label },
+            }
+        }
+        Some(TokenKind::Let) => {
+            tokens.next();
+            parse_let(tokens, literal_data)?
+        }
+        Some(TokenKind::Mutable) => {
+            tokens.next();
+            let name = expect_identifier(tokens, literal_data)?.clone();
+            expect_token(tokens, TokenKind::DefineVar)?;
+            let value = parse_expression(tokens, literal_data)?;
+            expect_token(tokens, TokenKind::EndOfStatement)?;
+            Statement::Let {
+                name,
+                ty: None,
+                value: Some(value),
+                mutable: true,
+            }
+        }
+        Some(TokenKind::Return) => {
+            tokens.next();
+            let value = match tokens.peek().map(Token::kind) {
+                Some(TokenKind::EndOfStatement | TokenKind::CloseBraces) => None,
+                _ => Some(parse_return_value(tokens, literal_data)?),
+            };
+            // without a `;` at the end of a block, the `return` is its value
+            if tokens.peek().map(Token::kind) == Some(TokenKind::CloseBraces) {
+                return Ok(BlockItem::Value(Expression::Return(value.map(Box::new))));
+            }
+            expect_token(tokens, TokenKind::EndOfStatement)?;
+            Statement::Return(value)
+        }
+        _ => {
+            let expression = parse_expression(tokens, literal_data)?;
+            match tokens.peek().map(Token::kind) {
+                Some(TokenKind::CloseBraces) => return Ok(BlockItem::Value(expression)),
+                Some(
+                    kind @ (TokenKind::Assign
+                    | TokenKind::PlusAssign
+                    | TokenKind::MinusAssign
+                    | TokenKind::MulAssign
+                    | TokenKind::DivAssign
+                    | TokenKind::RemAssign),
+                ) => {
+                    let assign = tokens.next().unwrap();
+                    if !expression.is_place() {
+                        return Err(ParseError {
+                            token: Some(assign),
+                            kind: ParseErrorKind::InvalidAssignmentTarget,
+                        });
+                    }
+                    let value = parse_expression(tokens, literal_data)?;
+                    expect_token(tokens, TokenKind::EndOfStatement)?;
+                    match compound_assignment_operator(kind) {
+                        Some(operator) => Statement::CompoundAssign {
+                            operator,
+                            target: expression,
+                            value,
+                        },
+                        None => Statement::Assign {
+                            target: expression,
+                            value,
+                        },
+                    }
+                }
+                Some(TokenKind::DefineVar) => {
+                    let define = tokens.next().unwrap();
+                    let Expression::Identifier(name) = expression else {
+                        return Err(ParseError {
+                            token: Some(define),
+                            kind: ParseErrorKind::InvalidAssignmentTarget,
+                        });
+                    };
+                    let value = parse_expression(tokens, literal_data)?;
+                    expect_token(tokens, TokenKind::EndOfStatement)?;
+                    Statement::Let {
+                        name,
+                        ty: None,
+                        value: Some(value),
+                        mutable: false,
+                    }
+                }
+                _ => {
+                    expect_token(tokens, TokenKind::EndOfStatement)?;
+                    Statement::Expression(expression)
+                }
+            }
+        }
+    };
+
+    Ok(BlockItem::Statement(statement))
 }
 
-fn parse_expression(
+/// The binary operator of a compound assignment like `+=`, or `None` for a
+/// plain `=`.
+fn compound_assignment_operator(kind: TokenKind) -> Option<TokenKind> {
+    match kind {
+        TokenKind::PlusAssign => Some(TokenKind::Plus),
+        TokenKind::MinusAssign => Some(TokenKind::Minus),
+        TokenKind::MulAssign => Some(TokenKind::Mul),
+        TokenKind::DivAssign => Some(TokenKind::Div),
+        TokenKind::RemAssign => Some(TokenKind::Rem),
+        _ => None,
+    }
+}
+
+/// Parses the rest of a `let name: Type = value;` after the `let`. The `mut`,
+/// the type and the value are all optional.
+fn parse_let(tokens: &mut TokenIter, literal_data: &LiteralData) -> Result<Statement, ParseError> {
+    let mutable = tokens
+        .next_if(|token| token.kind() == TokenKind::Mutable)
+        .is_some();
+    let name = expect_identifier(tokens, literal_data)?.clone();
+
+    let ty = match tokens.next_if(|token| token.kind() == TokenKind::FieldTypeSeparator) {
+        Some(_) => Some(parse_type(tokens, literal_data)?),
+        None => None,
+    };
+
+    let value = match tokens.next() {
+        Some(token) if token.kind() == TokenKind::Assign => {
+            let value = parse_expression(tokens, literal_data)?;
+            expect_token(tokens, TokenKind::EndOfStatement)?;
+            Some(value)
+        }
+        Some(token) if token.kind() == TokenKind::EndOfStatement => None,
+        token if ty.is_some() => {
+            err_expected(token, &[TokenKind::Assign, TokenKind::EndOfStatement])?
+        }
+        token => err_expected(
+            token,
+            &[
+                TokenKind::FieldTypeSeparator,
+                TokenKind::Assign,
+                TokenKind::EndOfStatement,
+            ],
+        )?,
+    };
+
+    Ok(Statement::Let {
+        name,
+        ty,
+        value,
+        mutable,
+    })
+}
+
+/// Parses the value of a `return`. Several comma-separated values are
+/// returned as a tuple, so `return a, b;` is the same as `return (a, b);`.
+fn parse_return_value(
     tokens: &mut TokenIter,
     literal_data: &LiteralData,
-) -> Result<Statement, ParseError> {
-    todo!()
+) -> Result<Expression, ParseError> {
+    let first = parse_expression(tokens, literal_data)?;
+    if tokens.peek().map(Token::kind) != Some(TokenKind::Comma) {
+        return Ok(first);
+    }
+
+    let mut elements = vec![first];
+    while tokens.peek().map(Token::kind) == Some(TokenKind::Comma) {
+        tokens.next();
+        elements.push(parse_expression(tokens, literal_data)?);
+    }
+
+    Ok(Expression::Tuple(elements))
+}
+
+/// Parses the rest of an `if` after the `if` keyword, including any `else`
+/// branches. An `else` always belongs to the nearest `if`, so an `else if`
+/// chain nests to the right.
+fn parse_if(tokens: &mut TokenIter, literal_data: &LiteralData) -> Result<Expression, ParseError> {
+    let condition = parse_condition(tokens, literal_data)?;
+    let open = expect_token(tokens, TokenKind::OpenBraces)?;
+    let body = parse_code_block(tokens, literal_data, &open)?;
+
+    let else_branch = match tokens.next_if(|token| token.kind() == TokenKind::Else) {
+        Some(_) => match tokens.next() {
+            Some(token) if token.kind() == TokenKind::If => {
+                Some(ElseBranch::If(Box::new(parse_if(tokens, literal_data)?)))
+            }
+            Some(token) if token.kind() == TokenKind::OpenBraces => Some(ElseBranch::Block(
+                parse_code_block(tokens, literal_data, &token)?,
+            )),
+            token => err_expected(token, &[TokenKind::If, TokenKind::OpenBraces])?,
+        },
+        None => None,
+    };
 
-    
-    // -, !, return, indentifier, literal, *, & 
+    Ok(Expression::If {
+        condition: Box::new(condition),
+        body,
+        else_branch,
+    })
 }
 
-#[derive(Debug)]
-struct Structure {
-    name: String,
-    fields: Vec<(String, String)>,
+/// Parses the rest of a `match` after the `match` keyword.
+fn parse_match(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+) -> Result<Expression, ParseError> {
+    let scrutinee = parse_expression_before_block(tokens, literal_data)?;
+    let open = expect_token(tokens, TokenKind::OpenBraces)?;
+    let arms = parse_bracketed_list(tokens, &open, &PATTERN_START, |tokens| {
+        let pattern = parse_pattern(tokens, literal_data)?;
+        expect_token(tokens, TokenKind::FatArrow)?;
+        let value = parse_expression(tokens, literal_data)?;
+        Ok((pattern, value))
+    })?;
+
+    Ok(Expression::Match {
+        scrutinee: Box::new(scrutinee),
+        arms,
+    })
 }
 
-#[derive(Debug)]
-struct Function {
-    name: String,
-    arguments: Vec<(String, String)>,
-    return_type: Option<String>,
-    body: CodeBlock,
+const PATTERN_START: [TokenKind; 4] = [
+    TokenKind::IntegerLiteral,
+    TokenKind::StringLiteral,
+    TokenKind::CharLiteral,
+    TokenKind::Underscore,
+];
+
+fn parse_pattern(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+) -> Result<Pattern, ParseError> {
+    let token = tokens.next();
+    let pattern = match &token {
+        Some(t) if t.kind() == TokenKind::Underscore => Some(Pattern::Wildcard),
+        Some(t) => literal_data
+            .try_get_integer_literal(t)
+            .map(|digits| Pattern::IntegerLiteral {
+                digits: digits.clone(),
+                suffix: literal_data.try_get_integer_suffix(t).cloned(),
+            })
+            .or_else(|| {
+                literal_data
+                    .try_get_string_literal(t)
+                    .map(|string| Pattern::StringLiteral(string.clone()))
+            })
+            .or_else(|| {
+                literal_data
+                    .try_get_char_literal(t)
+                    .map(Pattern::CharLiteral)
+            }),
+        None => None,
+    };
+
+    match pattern {
+        Some(pattern) => Ok(pattern),
+        None => err_expected(token, &PATTERN_START)?,
+    }
 }
 
-#[derive(Debug)]
-struct Statement {}
+/// Parses the condition of an `if` or `while`.
+fn parse_condition(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+) -> Result<Expression, ParseError> {
+    // a block is an expression, but here a `{` is always the body of a
+    // construct with a missing condition
+    if tokens.peek().map(Token::kind) == Some(TokenKind::OpenBraces) {
+        let expected: Vec<_> = EXPRESSION_START
+            .into_iter()
+            .filter(|kind| *kind != TokenKind::OpenBraces)
+            .collect();
+        err_expected(tokens.next(), &expected)?;
+    }
 
-#[derive(Debug)]
-struct CodeBlock {
-    statements: Vec<Statement>,
+    let condition = parse_expression_before_block(tokens, literal_data)?;
+    if let Some(assign) = tokens.next_if(|token| token.kind() == TokenKind::Assign) {
+        return Err(ParseError {
+            token: Some(assign),
+            kind: ParseErrorKind::AssignmentInCondition,
+        });
+    }
+    Ok(condition)
 }
 
-// TODO: this could probably be used with the `?` in the future
-fn err_expected(token: Option<Token>, expected: &[TokenKind]) -> Result<!, ParseError> {
-    Err(ParseError {
-        token,
-        expected: expected.to_vec(),
+/// Parses the rest of a `while` loop after the `while` keyword.
+fn parse_while(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+    label: Option<String>,
+) -> Result<Statement, ParseError> {
+    let condition = parse_condition(tokens, literal_data)?;
+    let open = expect_token(tokens, TokenKind::OpenBraces)?;
+    let body = parse_code_block(tokens, literal_data, &open)?;
+
+    Ok(Statement::While {
+        label,
+        condition,
+        body,
     })
 }
 
-fn expect_token(tokens: &mut TokenIter, kind: TokenKind) -> Result<Token, ParseError> {
-    let next_token = tokens.next();
-    match next_token {
-        Some(token) if token.kind() == kind => Ok(token),
-        _ => err_expected(next_token, &[kind])?,
-    }
+/// Binary operators and their precedences, a higher precedence binding
+/// tighter. All binary operators are left-associative.
+const BINARY_OPERATORS: [(TokenKind, u8); 18] = [
+    (TokenKind::LogicalOr, 1),
+    (TokenKind::LogicalAnd, 2),
+    (TokenKind::Or, 3),
+    (TokenKind::Xor, 4),
+    (TokenKind::And, 5),
+    (TokenKind::Equal, 6),
+    (TokenKind::NotEqual, 6),
+    (TokenKind::Greater, 7),
+    (TokenKind::GreaterOrEqual, 7),
+    (TokenKind::Less, 7),
+    (TokenKind::LessOrEqual, 7),
+    (TokenKind::ShiftLeft, 8),
+    (TokenKind::ShiftRight, 8),
+    (TokenKind::Plus, 9),
+    (TokenKind::Minus, 9),
+    (TokenKind::Mul, 10),
+    (TokenKind::Div, 10),
+    (TokenKind::Rem, 10),
+];
+
+/// `&&` is a unary operator only as two `&`s written together, like `&&x`.
+const UNARY_OPERATORS: [TokenKind; 5] = [
+    TokenKind::Minus,
+    TokenKind::Not,
+    TokenKind::Mul,
+    TokenKind::And,
+    TokenKind::LogicalAnd,
+];
+
+const EXPRESSION_START: [TokenKind; 16] = [
+    TokenKind::Identifier,
+    TokenKind::IntegerLiteral,
+    TokenKind::FloatLiteral,
+    TokenKind::StringLiteral,
+    TokenKind::CharLiteral,
+    TokenKind::BoolLiteral,
+    TokenKind::OpenParentheses,
+    TokenKind::OpenBraces,
+    TokenKind::If,
+    TokenKind::Match,
+    TokenKind::Return,
+    TokenKind::Minus,
+    TokenKind::Not,
+    TokenKind::Mul,
+    TokenKind::And,
+    TokenKind::LogicalAnd,
+];
+
+/// Whether `token` can start an expression. An `Error` token can too, but it
+/// isn't in `EXPRESSION_START` so that it isn't listed as expected.
+fn starts_expression(token: &Token) -> bool {
+    EXPRESSION_START.contains(&token.kind()) || token.kind() == TokenKind::Error
 }
 
-fn expect_identifier<'a>(
+fn binary_operator_precedence(kind: TokenKind) -> Option<u8> {
+    BINARY_OPERATORS
+        .iter()
+        .find(|(operator, _)| *operator == kind)
+        .map(|(_, precedence)| *precedence)
+}
+
+fn parse_expression(
     tokens: &mut TokenIter,
-    literal_data: &'a LiteralData,
-) -> Result<&'a String, ParseError> {
-    let token = tokens.next();
+    literal_data: &LiteralData,
+) -> Result<Expression, ParseError> {
+    parse_binary_expression(tokens, literal_data, 0, true)
+}
 
-    match token
-        .as_ref()
-        .and_then(|t| literal_data.try_get_identifier(t))
+/// Parses an expression that is followed by a block, like the condition of an
+/// `if`. A struct literal isn't allowed there outside of brackets, so that the
+/// `{` of `if a {}` opens the block instead of a literal of a struct `a`.
+fn parse_expression_before_block(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+) -> Result<Expression, ParseError> {
+    parse_binary_expression(tokens, literal_data, 0, false)
+}
+
+/// Parses a chain of binary operators that bind at least as tightly as
+/// `min_precedence`.
+fn parse_binary_expression(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+    min_precedence: u8,
+    struct_literals: bool,
+) -> Result<Expression, ParseError> {
+    let mut left = parse_unary_expression(tokens, literal_data, struct_literals)?;
+
+    while let Some(precedence) = tokens
+        .peek()
+        .and_then(|token| binary_operator_precedence(token.kind()))
+        .filter(|precedence| *precedence >= min_precedence)
     {
-        Some(name) => Ok(name),
-        None => err_expected(token, &[TokenKind::Identifier])?,
+        let operator = tokens.next().unwrap().kind();
+        let right = parse_binary_expression(tokens, literal_data, precedence + 1, struct_literals)?;
+        left = Expression::Binary {
+            operator,
+            left: Box::new(left),
+            right: Box::new(right),
+        };
+    }
+
+    Ok(left)
+}
+
+fn parse_unary_expression(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+    struct_literals: bool,
+) -> Result<Expression, ParseError> {
+    match tokens.peek().map(Token::kind) {
+        Some(operator) if UNARY_OPERATORS.contains(&operator) => {
+            let operator_token = tokens.next().unwrap();
+            let has_operand = tokens.peek().is_some_and(starts_expression);
+            if !has_operand {
+                return Err(ParseError {
+                    token: Some(operator_token),
+                    kind: ParseErrorKind::MissingOperand,
+                });
+            }
+            let mut operand = parse_unary_expression(tokens, literal_data, struct_literals)?;
+            let operator = match operator {
+                TokenKind::LogicalAnd => {
+                    operand = Expression::Unary {
+                        operator: TokenKind::And,
+                        operand: Box::new(operand),
+                    };
+                    TokenKind::And
+                }
+                operator => operator,
+            };
+            Ok(Expression::Unary {
+                operator,
+                operand: Box::new(operand),
+            })
+        }
+        _ => parse_postfix_expression(tokens, literal_data, struct_literals),
+    }
+}
+
+/// Parses a primary expression followed by any number of field accesses,
+/// calls and indexing operations, applied left to right.
+fn parse_postfix_expression(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+    struct_literals: bool,
+) -> Result<Expression, ParseError> {
+    let mut expression = parse_primary_expression(tokens, literal_data, struct_literals)?;
+
+    loop {
+        expression = match tokens.peek().map(Token::kind) {
+            Some(TokenKind::Dot) => {
+                tokens.next();
+                let field = expect_identifier(tokens, literal_data)?.clone();
+                Expression::Field {
+                    base: Box::new(expression),
+                    field,
+                }
+            }
+            Some(TokenKind::OpenParentheses) => {
+                let open = tokens.next().unwrap();
+                let arguments = parse_expression_list(tokens, literal_data, &open)?;
+                Expression::Call {
+                    callee: Box::new(expression),
+                    arguments,
+                }
+            }
+            Some(TokenKind::OpenBrackets) => {
+                let open = tokens.next().unwrap();
+                let index = parse_expression(tokens, literal_data)?;
+                expect_closing_bracket(tokens, &open)?;
+                Expression::Index {
+                    base: Box::new(expression),
+                    index: Box::new(index),
+                }
+            }
+            // input that failed to tokenize between two operands, like the `@`
+            // of `1 @ 2`, swallows the operand after it
+            Some(TokenKind::Error) => {
+                tokens.next();
+                if tokens.peek().is_some_and(starts_expression) {
+                    parse_unary_expression(tokens, literal_data, struct_literals)?;
+                }
+                Expression::Error
+            }
+            _ => break,
+        };
+    }
+
+    Ok(expression)
+}
+
+fn parse_primary_expression(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+    struct_literals: bool,
+) -> Result<Expression, ParseError> {
+    let token = tokens.next();
+    let expression = match &token {
+        Some(t) if t.kind() == TokenKind::OpenParentheses => {
+            return parse_parenthesized_expression(tokens, literal_data, t);
+        }
+        Some(t) if t.kind() == TokenKind::OpenBraces => {
+            return Ok(Expression::Block(parse_code_block(
+                tokens,
+                literal_data,
+                t,
+            )?));
+        }
+        Some(t) if t.kind() == TokenKind::Error => Some(Expression::Error),
+        Some(t) if t.kind() == TokenKind::If => return parse_if(tokens, literal_data),
+        Some(t) if t.kind() == TokenKind::Match => return parse_match(tokens, literal_data),
+        Some(t)
+            if struct_literals
+                && t.kind() == TokenKind::Identifier
+                && tokens.peek().map(Token::kind) == Some(TokenKind::OpenBraces) =>
+        {
+            let name = literal_data.try_get_identifier(t).unwrap().clone();
+            let open = tokens.next().unwrap();
+            let fields = parse_bracketed_list(tokens, &open, &[TokenKind::Identifier], |tokens| {
+                parse_field_value(tokens, literal_data)
+            })?;
+            return Ok(Expression::StructLiteral { name, fields });
+        }
+        // `return` in an expression never produces a value, as in
+        // `x := y | return;`
+        Some(t) if t.kind() == TokenKind::Return => {
+            let has_operand = tokens.peek().is_some_and(starts_expression);
+            let operand = if has_operand {
+                Some(Box::new(parse_expression(tokens, literal_data)?))
+            } else {
+                None
+            };
+            return Ok(Expression::Return(operand));
+        }
+        Some(t) => literal_data
+            .try_get_identifier(t)
+            .map(|name| Expression::Identifier(name.clone()))
+            .or_else(|| {
+                literal_data
+                    .try_get_integer_literal(t)
+                    .map(|digits| Expression::IntegerLiteral {
+                        digits: digits.clone(),
+                        suffix: literal_data.try_get_integer_suffix(t).cloned(),
+                    })
+            })
+            .or_else(|| {
+                literal_data
+                    .try_get_float_literal(t)
+                    .map(|text| Expression::FloatLiteral(text.clone()))
+            })
+            .or_else(|| {
+                literal_data
+                    .try_get_string_literal(t)
+                    .map(|string| Expression::StringLiteral(string.clone()))
+            })
+            .or_else(|| {
+                literal_data
+                    .try_get_char_literal(t)
+                    .map(Expression::CharLiteral)
+            })
+            .or_else(|| {
+                literal_data
+                    .try_get_bool_literal(t)
+                    .map(Expression::BoolLiteral)
+            }),
+        None => None,
+    };
+
+    match expression {
+        Some(expression) => Ok(expression),
+        None => err_expected(token, &EXPRESSION_START)?,
+    }
+}
+
+/// Parses a `field: value` pair of a struct literal.
+fn parse_field_value(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+) -> Result<(String, Expression), ParseError> {
+    let field = expect_identifier(tokens, literal_data)?.clone();
+    expect_token(tokens, TokenKind::FieldTypeSeparator)?;
+    let value = parse_expression(tokens, literal_data)?;
+    Ok((field, value))
+}
+
+/// Parses a parenthesized expression or a tuple after the `(`. Only a comma
+/// makes a tuple, so `(a)` is just `a` but `(a,)` is a one-element tuple.
+fn parse_parenthesized_expression(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+    open: &Token,
+) -> Result<Expression, ParseError> {
+    if tokens.peek().map(Token::kind) == Some(TokenKind::CloseParentheses) {
+        tokens.next();
+        return Ok(Expression::Tuple(Vec::new()));
+    }
+
+    let first = parse_expression(tokens, literal_data)?;
+
+    let next_token = tokens.next();
+    match next_token.as_ref().map(Token::kind) {
+        Some(TokenKind::CloseParentheses) => Ok(first),
+        Some(TokenKind::Comma) => {
+            let mut elements = vec![first];
+            elements.extend(parse_expression_list(tokens, literal_data, open)?);
+            Ok(Expression::Tuple(elements))
+        }
+        _ => err_expected_in_brackets(
+            next_token,
+            &[TokenKind::Comma, TokenKind::CloseParentheses],
+            open,
+        )?,
+    }
+}
+
+/// Parses comma-separated expressions up to and including the bracket
+/// closing `open`, allowing a trailing comma.
+fn parse_expression_list(
+    tokens: &mut TokenIter,
+    literal_data: &LiteralData,
+    open: &Token,
+) -> Result<Vec<Expression>, ParseError> {
+    parse_bracketed_list(tokens, open, &EXPRESSION_START, |tokens| {
+        parse_expression(tokens, literal_data)
+    })
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Structure {
+    pub name: String,
+    pub location: Location,
+    pub attributes: Vec<Attribute>,
+    pub type_params: Vec<String>,
+    pub fields: Vec<(String, Type)>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Function {
+    pub name: String,
+    pub location: Location,
+    pub attributes: Vec<Attribute>,
+    pub type_params: Vec<String>,
+    pub arguments: Vec<Argument>,
+    /// `Type::Unit` when the function has no `->`.
+    pub return_type: Type,
+    /// The `value` of the body is returned from the function; whether it
+    /// matches `return_type` is left to type checking.
+    pub body: CodeBlock,
+}
+
+/// The path of a `use`. The locations are those of the first segment.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ImportPath {
+    /// `use std::io;`
+    Single {
+        segments: Vec<String>,
+        location: Location,
+    },
+    /// `use std::io::*;`
+    Glob {
+        prefix: Vec<String>,
+        location: Location,
+    },
+    /// `use std::{io, fmt::Display};`
+    Group {
+        prefix: Vec<String>,
+        members: Vec<Vec<String>>,
+        location: Location,
+    },
+}
+
+/// An attribute like `#[inline]` or `#[doc("text")]`, attached to the
+/// definition after it.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Attribute {
+    pub name: String,
+    pub location: Location,
+    pub args: Vec<Expression>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Argument {
+    pub name: String,
+    pub mutable: bool,
+    pub argument_type: Type,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Type {
+    Named {
+        name: String,
+        args: Vec<Type>,
+    },
+    Reference(Box<Type>),
+    /// `()`, which is never a `Tuple`.
+    Unit,
+    Tuple(Vec<Type>),
+    Array(Box<Type>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Statement {
+    Expression(Expression),
+    While {
+        label: Option<String>,
+        condition: Expression,
+        body: CodeBlock,
+    },
+    Break {
+        label: Option<String>,
+    },
+    Continue {
+        label: Option<String>,
+    },
+    Return(Option<Expression>),
+    Assign {
+        target: Expression,
+        value: Expression,
+    },
+    /// `target += value` and the like, `operator` being the binary operator
+    /// such as `TokenKind::Plus`.
+    CompoundAssign {
+        operator: TokenKind,
+        target: Expression,
+        value: Expression,
+    },
+    /// `let name: ty = value;`, or `name := value;` which is the same as
+    /// `let name = value;` and `mut name := value;` which is the same as
+    /// `let mut name = value;`. A `let` without a value declares the variable
+    /// without assigning to it.
+    Let {
+        name: String,
+        ty: Option<Type>,
+        value: Option<Expression>,
+        mutable: bool,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ElseBranch {
+    Block(CodeBlock),
+    /// An `else if`, always an `Expression::If`.
+    If(Box<Expression>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expression {
+    Identifier(String),
+    /// The digits as written, and the type suffix, like the `u8` of `255u8`.
+    IntegerLiteral {
+        digits: String,
+        suffix: Option<String>,
+    },
+    FloatLiteral(String),
+    StringLiteral(String),
+    CharLiteral(char),
+    BoolLiteral(bool),
+    Tuple(Vec<Expression>),
+    Unary {
+        operator: TokenKind,
+        operand: Box<Expression>,
+    },
+    Binary {
+        operator: TokenKind,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    Field {
+        base: Box<Expression>,
+        field: String,
+    },
+    Index {
+        base: Box<Expression>,
+        index: Box<Expression>,
+    },
+    Call {
+        callee: Box<Expression>,
+        arguments: Vec<Expression>,
+    },
+    If {
+        condition: Box<Expression>,
+        body: CodeBlock,
+        else_branch: Option<ElseBranch>,
+    },
+    Block(CodeBlock),
+    Return(Option<Box<Expression>>),
+    /// `Name { field: value }`
+    StructLiteral {
+        name: String,
+        fields: Vec<(String, Expression)>,
+    },
+    /// `match x { 1 => a, _ => b }`, the arms tried in order.
+    Match {
+        scrutinee: Box<Expression>,
+        arms: Vec<(Pattern, Expression)>,
+    },
+    /// Input that failed to tokenize, which has already been reported.
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pattern {
+    IntegerLiteral {
+        digits: String,
+        suffix: Option<String>,
+    },
+    StringLiteral(String),
+    CharLiteral(char),
+    /// `_`, which matches anything.
+    Wildcard,
+}
+
+impl Expression {
+    /// Whether the expression refers to a memory location, and so can be
+    /// assigned to.
+    fn is_place(&self) -> bool {
+        match self {
+            Self::Identifier(_) => true,
+            Self::Unary {
+                operator: TokenKind::Mul,
+                ..
+            } => true,
+            Self::Field { base, .. } | Self::Index { base, .. } => base.is_place(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CodeBlock {
+    pub statements: Vec<Statement>,
+    /// The expression at the end of the block without a `;` after it.
+    pub value: Option<Box<Expression>>,
+}
+
+/// Prints the program in the syntax of the language: imports first, then
+/// structs and then functions, each separated by an empty line. Literals and
+/// nesting are kept, but comments and the original formatting aren't.
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        let mut separate = |f: &mut fmt::Formatter<'_>| {
+            if !mem::take(&mut first) {
+                writeln!(f)?;
+            }
+            Ok(())
+        };
+
+        if !self.imports.is_empty() {
+            separate(f)?;
+            for import in &self.imports {
+                writeln!(f, "{}", import)?;
+            }
+        }
+        for structure in &self.structs {
+            separate(f)?;
+            writeln!(f, "{}", structure)?;
+        }
+        for function in &self.functions {
+            separate(f)?;
+            writeln!(f, "{}", function)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Structure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for attribute in &self.attributes {
+            writeln!(f, "{}", attribute)?;
+        }
+        write!(f, "struct {}", self.name)?;
+        fmt_type_params(f, &self.type_params)?;
+        if self.fields.is_empty() {
+            return write!(f, " {{}}");
+        }
+        writeln!(f, " {{")?;
+        for (name, field_type) in &self.fields {
+            writeln!(f, "    {}: {},", name, field_type)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for attribute in &self.attributes {
+            writeln!(f, "{}", attribute)?;
+        }
+        write!(f, "fn {}", self.name)?;
+        fmt_type_params(f, &self.type_params)?;
+        write!(f, "(")?;
+        for (i, argument) in self.arguments.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            if argument.mutable {
+                write!(f, "mut ")?;
+            }
+            write!(f, "{}: {}", argument.name, argument.argument_type)?;
+        }
+        write!(f, ")")?;
+        if self.return_type != Type::Unit {
+            write!(f, " -> {}", self.return_type)?;
+        }
+        write!(f, " ")?;
+        fmt_code_block(f, &self.body, 0)
+    }
+}
+
+impl fmt::Display for ImportPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Single { segments, .. } => write!(f, "use {};", segments.join("::")),
+            Self::Glob { prefix, .. } => write!(f, "use {}::*;", prefix.join("::")),
+            Self::Group {
+                prefix, members, ..
+            } => {
+                let members: Vec<_> = members.iter().map(|member| member.join("::")).collect();
+                write!(f, "use {}::{{{}}};", prefix.join("::"), members.join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#[{}", self.name)?;
+        if !self.args.is_empty() {
+            write!(f, "(")?;
+            fmt_list(f, &self.args)?;
+            write!(f, ")")?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Named { name, args } => {
+                write!(f, "{}", name)?;
+                if !args.is_empty() {
+                    write!(f, "(")?;
+                    fmt_list(f, args)?;
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            Self::Reference(inner) => write!(f, "&{}", inner),
+            Self::Unit => write!(f, "()"),
+            // a one-element tuple needs a comma to not be just parentheses
+            Self::Tuple(types) if types.len() == 1 => write!(f, "({},)", types[0]),
+            Self::Tuple(types) => {
+                write!(f, "(")?;
+                fmt_list(f, types)?;
+                write!(f, ")")
+            }
+            Self::Array(element_type) => write!(f, "[{}]", element_type),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IntegerLiteral { digits, suffix } => {
+                write!(f, "{}{}", digits, suffix.as_deref().unwrap_or(""))
+            }
+            // the escapes of `Debug` are all valid in the language too
+            Self::StringLiteral(string) => write!(f, "{:?}", string),
+            Self::CharLiteral(c) => write!(f, "{:?}", c),
+            Self::Wildcard => write!(f, "_"),
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_expression(f, self, 0)
+    }
+}
+
+fn fmt_type_params(f: &mut fmt::Formatter<'_>, type_params: &[String]) -> fmt::Result {
+    if type_params.is_empty() {
+        return Ok(());
+    }
+    write!(f, "<{}>", type_params.join(", "))
+}
+
+fn fmt_list<T: fmt::Display>(f: &mut fmt::Formatter<'_>, items: &[T]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+fn fmt_indent(f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+    write!(f, "{}", "    ".repeat(indent))
+}
+
+/// Prints a block whose `{` is on the current line and whose contents are
+/// indented one level deeper than `indent`.
+fn fmt_code_block(f: &mut fmt::Formatter<'_>, block: &CodeBlock, indent: usize) -> fmt::Result {
+    if block.statements.is_empty() && block.value.is_none() {
+        return write!(f, "{{}}");
+    }
+    writeln!(f, "{{")?;
+    for statement in &block.statements {
+        fmt_indent(f, indent + 1)?;
+        fmt_statement(f, statement, indent + 1)?;
+        writeln!(f)?;
+    }
+    if let Some(value) = &block.value {
+        fmt_indent(f, indent + 1)?;
+        fmt_expression_at(f, value, true, indent + 1)?;
+        writeln!(f)?;
+    }
+    fmt_indent(f, indent)?;
+    write!(f, "}}")
+}
+
+fn fmt_statement(f: &mut fmt::Formatter<'_>, statement: &Statement, indent: usize) -> fmt::Result {
+    match statement {
+        Statement::Expression(expression) if is_block_like(expression) => {
+            fmt_expression(f, expression, indent)
+        }
+        Statement::Expression(expression) => {
+            fmt_expression_at(f, expression, true, indent)?;
+            write!(f, ";")
+        }
+        Statement::While {
+            label,
+            condition,
+            body,
+        } => {
+            if let Some(label) = label {
+                write!(f, "'{}: ", label)?;
+            }
+            write!(f, "while ")?;
+            fmt_expression(f, condition, indent)?;
+            write!(f, " ")?;
+            fmt_code_block(f, body, indent)
+        }
+        Statement::Break { label } => match label {
+            Some(label) => write!(f, "break '{};", label),
+            None => write!(f, "break;"),
+        },
+        Statement::Continue { label } => match label {
+            Some(label) => write!(f, "continue '{};", label),
+            None => write!(f, "continue;"),
+        },
+        Statement::Return(value) => {
+            write!(f, "return")?;
+            if let Some(value) = value {
+                write!(f, " ")?;
+                fmt_expression(f, value, indent)?;
+            }
+            write!(f, ";")
+        }
+        Statement::Assign { target, value } => {
+            fmt_expression_at(f, target, true, indent)?;
+            write!(f, " = ")?;
+            fmt_expression(f, value, indent)?;
+            write!(f, ";")
+        }
+        Statement::CompoundAssign {
+            operator,
+            target,
+            value,
+        } => {
+            fmt_expression_at(f, target, true, indent)?;
+            write!(f, " {}= ", operator)?;
+            fmt_expression(f, value, indent)?;
+            write!(f, ";")
+        }
+        Statement::Let {
+            name,
+            ty,
+            value,
+            mutable,
+        } => {
+            write!(f, "let ")?;
+            if *mutable {
+                write!(f, "mut ")?;
+            }
+            write!(f, "{}", name)?;
+            if let Some(ty) = ty {
+                write!(f, ": {}", ty)?;
+            }
+            if let Some(value) = value {
+                write!(f, " = ")?;
+                fmt_expression(f, value, indent)?;
+            }
+            write!(f, ";")
+        }
+    }
+}
+
+/// Prints `expression`, in parentheses if it binds more loosely than
+/// `precedence`, the precedence of the binary operator it is an operand of.
+fn fmt_operand(
+    f: &mut fmt::Formatter<'_>,
+    expression: &Expression,
+    precedence: u8,
+    at_statement_start: bool,
+    indent: usize,
+) -> fmt::Result {
+    let needs_parentheses = match expression {
+        Expression::Binary { operator, .. } => {
+            binary_operator_precedence(*operator).is_some_and(|own| own < precedence)
+        }
+        Expression::Return(_) => true,
+        _ => at_statement_start && is_block_like(expression),
+    };
+    if needs_parentheses {
+        write!(f, "(")?;
+        fmt_expression(f, expression, indent)?;
+        write!(f, ")")
+    } else {
+        fmt_expression_at(f, expression, at_statement_start, indent)
+    }
+}
+
+/// Whether `expression` ends at its closing brace when it starts a statement,
+/// so that it has to be in parentheses to be the operand of anything there.
+fn is_block_like(expression: &Expression) -> bool {
+    matches!(
+        expression,
+        Expression::If { .. } | Expression::Match { .. } | Expression::Block(_)
+    )
+}
+
+fn fmt_expression(
+    f: &mut fmt::Formatter<'_>,
+    expression: &Expression,
+    indent: usize,
+) -> fmt::Result {
+    fmt_expression_at(f, expression, false, indent)
+}
+
+/// Prints `expression`, which starts a statement if `at_statement_start`, in
+/// which case a block-like expression leading it gets parentheses, as in
+/// `(if a { 1 } else { 2 }) + 1;`.
+fn fmt_expression_at(
+    f: &mut fmt::Formatter<'_>,
+    expression: &Expression,
+    at_statement_start: bool,
+    indent: usize,
+) -> fmt::Result {
+    // operators and postfix operations bind tighter than any binary operator
+    const POSTFIX_PRECEDENCE: u8 = u8::MAX;
+
+    match expression {
+        Expression::Identifier(name) => write!(f, "{}", name),
+        Expression::IntegerLiteral { digits, suffix } => {
+            write!(f, "{}{}", digits, suffix.as_deref().unwrap_or(""))
+        }
+        Expression::FloatLiteral(text) => write!(f, "{}", text),
+        Expression::StringLiteral(string) => write!(f, "{:?}", string),
+        Expression::CharLiteral(c) => write!(f, "{:?}", c),
+        Expression::BoolLiteral(value) => write!(f, "{}", value),
+        Expression::Error => write!(f, "{}", TokenKind::Error),
+        // a one-element tuple needs a comma to not be just parentheses
+        Expression::Tuple(elements) if elements.len() == 1 => {
+            write!(f, "(")?;
+            fmt_expression(f, &elements[0], indent)?;
+            write!(f, ",)")
+        }
+        Expression::Tuple(elements) => {
+            write!(f, "(")?;
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                fmt_expression(f, element, indent)?;
+            }
+            write!(f, ")")
+        }
+        Expression::Unary { operator, operand } => {
+            write!(f, "{}", operator)?;
+            fmt_operand(f, operand, POSTFIX_PRECEDENCE, false, indent)
+        }
+        Expression::Binary {
+            operator,
+            left,
+            right,
+        } => {
+            let precedence = binary_operator_precedence(*operator)
+                .expect("ICE: binary expression without a binary operator");
+            fmt_operand(f, left, precedence, at_statement_start, indent)?;
+            write!(f, " {} ", operator)?;
+            // all binary operators are left-associative
+            fmt_operand(f, right, precedence + 1, false, indent)
+        }
+        Expression::Field { base, field } => {
+            fmt_postfix_base(f, base, at_statement_start, indent)?;
+            write!(f, ".{}", field)
+        }
+        Expression::Index { base, index } => {
+            fmt_postfix_base(f, base, at_statement_start, indent)?;
+            write!(f, "[")?;
+            fmt_expression(f, index, indent)?;
+            write!(f, "]")
+        }
+        Expression::Call { callee, arguments } => {
+            fmt_postfix_base(f, callee, at_statement_start, indent)?;
+            write!(f, "(")?;
+            for (i, argument) in arguments.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                fmt_expression(f, argument, indent)?;
+            }
+            write!(f, ")")
+        }
+        Expression::If {
+            condition,
+            body,
+            else_branch,
+        } => {
+            write!(f, "if ")?;
+            fmt_expression(f, condition, indent)?;
+            write!(f, " ")?;
+            fmt_code_block(f, body, indent)?;
+            match else_branch {
+                Some(ElseBranch::Block(block)) => {
+                    write!(f, " else ")?;
+                    fmt_code_block(f, block, indent)
+                }
+                Some(ElseBranch::If(if_expression)) => {
+                    write!(f, " else ")?;
+                    fmt_expression(f, if_expression, indent)
+                }
+                None => Ok(()),
+            }
+        }
+        Expression::Block(block) => fmt_code_block(f, block, indent),
+        Expression::Return(value) => {
+            write!(f, "return")?;
+            if let Some(value) = value {
+                write!(f, " ")?;
+                fmt_expression(f, value, indent)?;
+            }
+            Ok(())
+        }
+        Expression::StructLiteral { name, fields } => {
+            write!(f, "{} {{", name)?;
+            for (i, (field, value)) in fields.iter().enumerate() {
+                write!(f, "{}{}: ", if i > 0 { ", " } else { " " }, field)?;
+                fmt_expression(f, value, indent)?;
+            }
+            write!(f, "{}}}", if fields.is_empty() { "" } else { " " })
+        }
+        Expression::Match { scrutinee, arms } => {
+            write!(f, "match ")?;
+            fmt_expression(f, scrutinee, indent)?;
+            if arms.is_empty() {
+                return write!(f, " {{}}");
+            }
+            writeln!(f, " {{")?;
+            for (pattern, value) in arms {
+                fmt_indent(f, indent + 1)?;
+                write!(f, "{} => ", pattern)?;
+                fmt_expression(f, value, indent + 1)?;
+                writeln!(f, ",")?;
+            }
+            fmt_indent(f, indent)?;
+            write!(f, "}}")
+        }
+    }
+}
+
+/// Prints the expression that a field access, call or index applies to.
+fn fmt_postfix_base(
+    f: &mut fmt::Formatter<'_>,
+    base: &Expression,
+    at_statement_start: bool,
+    indent: usize,
+) -> fmt::Result {
+    let needs_parentheses = match base {
+        Expression::Unary { .. } | Expression::Binary { .. } | Expression::Return(_) => true,
+        _ => at_statement_start && is_block_like(base),
+    };
+    if needs_parentheses {
+        write!(f, "(")?;
+        fmt_expression(f, base, indent)?;
+        write!(f, ")")
+    } else {
+        fmt_expression_at(f, base, at_statement_start, indent)
+    }
+}
+
+// TODO: this could probably be used with the `?` in the future
+fn err_expected(token: Option<Token>, expected: &[TokenKind]) -> Result<!, ParseError> {
+    Err(ParseError {
+        token,
+        kind: ParseErrorKind::Expected(expected.to_vec()),
+    })
+}
+
+/// Like `err_expected`, but reports an unexpected closing bracket as not
+/// matching the bracket `open`.
+fn err_expected_in_brackets(
+    token: Option<Token>,
+    expected: &[TokenKind],
+    open: &Token,
+) -> Result<!, ParseError> {
+    match token.as_ref().map(Token::kind) {
+        Some(found) if is_closing_bracket(found) && !expected.contains(&found) => Err(ParseError {
+            token,
+            kind: ParseErrorKind::MismatchedBracket {
+                opened: open.kind(),
+                open_location: open.location(),
+                found,
+            },
+        }),
+        _ => err_expected(token, expected),
+    }
+}
+
+fn closing_bracket(open: TokenKind) -> TokenKind {
+    match open {
+        TokenKind::OpenParentheses => TokenKind::CloseParentheses,
+        TokenKind::OpenBrackets => TokenKind::CloseBrackets,
+        TokenKind::OpenBraces => TokenKind::CloseBraces,
+        _ => panic!("ICE: {:?} is not an opening bracket", open),
+    }
+}
+
+const fn is_closing_bracket(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::CloseParentheses | TokenKind::CloseBrackets | TokenKind::CloseBraces
+    )
+}
+
+fn expect_token(tokens: &mut TokenIter, kind: TokenKind) -> Result<Token, ParseError> {
+    let next_token = tokens.next();
+    match next_token {
+        Some(token) if token.kind() == kind => Ok(token),
+        _ => err_expected(next_token, &[kind])?,
+    }
+}
+
+fn expect_identifier<'a>(
+    tokens: &mut TokenIter,
+    literal_data: &'a LiteralData,
+) -> Result<&'a String, ParseError> {
+    expect_identifier_with_location(tokens, literal_data).map(|(name, _)| name)
+}
+
+fn expect_closing_bracket(tokens: &mut TokenIter, open: &Token) -> Result<Token, ParseError> {
+    let end_token = closing_bracket(open.kind());
+    let next_token = tokens.next();
+    match next_token {
+        Some(token) if token.kind() == end_token => Ok(token),
+        _ => err_expected_in_brackets(next_token, &[end_token], open)?,
+    }
+}
+
+fn expect_label<'a>(
+    tokens: &mut TokenIter,
+    literal_data: &'a LiteralData,
+) -> Result<&'a String, ParseError> {
+    let token = tokens.next();
+
+    match token.as_ref().and_then(|t| literal_data.try_get_label(t)) {
+        Some(label) => Ok(label),
+        None => err_expected(token, &[TokenKind::Label])?,
+    }
+}
+
+fn expect_identifier_with_location<'a>(
+    tokens: &mut TokenIter,
+    literal_data: &'a LiteralData,
+) -> Result<(&'a String, Location), ParseError> {
+    let token = tokens.next();
+
+    match token
+        .as_ref()
+        .and_then(|t| Some((literal_data.try_get_identifier(t)?, t.location())))
+    {
+        Some(identifier) => Ok(identifier),
+        None => err_expected(token, &[TokenKind::Identifier])?,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::{tokenize_text, tokenize_text_with_options, HashRole, TokenizerOptions};
+
+    fn token_iter(input: &str) -> (TokenIter, LiteralData) {
+        let (tokens, literal_data) = tokenize_text(input).unwrap();
+        (tokens.into_iter().peekable(), literal_data)
+    }
+
+    fn named(name: &str) -> Type {
+        Type::Named {
+            name: name.to_string(),
+            args: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_return_type_identifier() {
+        let (mut tokens, literal_data) = token_iter("-> Int {");
+        let return_type = parse_return_type(&mut tokens, &literal_data).unwrap();
+        assert_eq!(return_type, named("Int"));
+        assert_eq!(tokens.next().map(|t| t.kind()), Some(TokenKind::OpenBraces));
+    }
+
+    #[test]
+    fn test_return_type_reference() {
+        let (mut tokens, literal_data) = token_iter("-> &Int {");
+        let return_type = parse_return_type(&mut tokens, &literal_data).unwrap();
+        assert_eq!(return_type, Type::Reference(Box::new(named("Int"))));
+    }
+
+    #[test]
+    fn test_return_type_tuple() {
+        let (mut tokens, literal_data) = token_iter("-> (Int, Int) {");
+        let return_type = parse_return_type(&mut tokens, &literal_data).unwrap();
+        assert_eq!(return_type, Type::Tuple(vec![named("Int"), named("Int")]));
+    }
+
+    #[test]
+    fn test_parenthesized_types() {
+        assert_eq!(parse_type_text("(Int)").unwrap(), named("Int"));
+        assert_eq!(
+            parse_type_text("((&Int))").unwrap(),
+            parse_type_text("&Int").unwrap()
+        );
+        assert_eq!(
+            parse_type_text("(Int,)").unwrap(),
+            Type::Tuple(vec![named("Int")])
+        );
+        assert_eq!(
+            parse_type_text("(Int, bool,)").unwrap(),
+            Type::Tuple(vec![named("Int"), named("bool")])
+        );
+        assert_eq!(
+            parse_type_text("List(Int,)").unwrap(),
+            generic("List", vec![named("Int")])
+        );
+        assert_eq!(Type::Tuple(vec![named("Int")]).to_string(), "(Int,)");
+
+        let error = parse_type_text("(Int bool)").unwrap_err();
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::Expected(vec![TokenKind::Comma, TokenKind::CloseParentheses])
+        );
+        assert!(parse_type_text("(,)").is_err());
+    }
+
+    #[test]
+    fn test_no_return_type() {
+        let (mut tokens, literal_data) = token_iter("{");
+        assert_eq!(
+            parse_return_type(&mut tokens, &literal_data).unwrap(),
+            Type::Unit
+        );
+    }
+
+    fn parse_struct_fields(input: &str) -> Vec<(String, Type)> {
+        let (tokens, literal_data) = tokenize_text(input).unwrap();
+        let mut program = Program::from_tokens(tokens, literal_data).unwrap();
+        program.structs.remove(0).fields
+    }
+
+    #[test]
+    fn test_struct_reference_field() {
+        let fields = parse_struct_fields("struct S { x: &Foo }");
+        assert_eq!(
+            fields,
+            vec![("x".to_string(), Type::Reference(Box::new(named("Foo"))))]
+        );
+    }
+
+    #[test]
+    fn test_struct_array_field() {
+        let fields = parse_struct_fields("struct S { x: [Int], y: Int }");
+        assert_eq!(
+            fields,
+            vec![
+                ("x".to_string(), Type::Array(Box::new(named("Int")))),
+                ("y".to_string(), named("Int")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_struct_generic_field() {
+        let fields = parse_struct_fields("struct S { x: Map(String, Int) }");
+        assert_eq!(
+            fields,
+            vec![(
+                "x".to_string(),
+                Type::Named {
+                    name: "Map".to_string(),
+                    args: vec![named("String"), named("Int")],
+                }
+            )]
+        );
+    }
+
+    fn parse_type_text(input: &str) -> Result<Type, ParseError> {
+        let (mut tokens, literal_data) = token_iter(input);
+        let parsed = parse_type(&mut tokens, &literal_data)?;
+        assert_eq!(tokens.next().map(|t| t.kind()), Some(TokenKind::EndOfFile));
+        Ok(parsed)
+    }
+
+    fn generic(name: &str, args: Vec<Type>) -> Type {
+        Type::Named {
+            name: name.to_string(),
+            args,
+        }
+    }
+
+    #[test]
+    fn test_generic_types() {
+        assert_eq!(parse_type_text("Int").unwrap(), named("Int"));
+        assert_eq!(parse_type_text("Foo()").unwrap(), named("Foo"));
+        assert_eq!(
+            parse_type_text("List(Int)").unwrap(),
+            generic("List", vec![named("Int")])
+        );
+        assert_eq!(
+            parse_type_text("A(B(C), &D(E))").unwrap(),
+            generic(
+                "A",
+                vec![
+                    generic("B", vec![named("C")]),
+                    Type::Reference(Box::new(generic("D", vec![named("E")]))),
+                ]
+            )
+        );
+
+        let (mut tokens, literal_data) = token_iter("-> Map(String, List(Int)) {");
+        assert_eq!(
+            parse_return_type(&mut tokens, &literal_data).unwrap(),
+            generic(
+                "Map",
+                vec![named("String"), generic("List", vec![named("Int")])]
+            )
+        );
+
+        let error = parse_type_text("A(").unwrap_err();
+        assert_eq!(error.token().map(Token::kind), Some(TokenKind::EndOfFile));
+        let error = parse_type_text("A(B C)").unwrap_err();
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::Expected(vec![TokenKind::Comma, TokenKind::CloseParentheses])
+        );
+    }
+
+    fn parse_expression_text(input: &str) -> Expression {
+        let (mut tokens, literal_data) = token_iter(input);
+        let expression = parse_expression(&mut tokens, &literal_data).unwrap();
+        assert_eq!(
+            tokens.next().map(|token| token.kind()),
+            Some(TokenKind::EndOfFile)
+        );
+        expression
+    }
+
+    fn identifier(name: &str) -> Expression {
+        Expression::Identifier(name.to_string())
+    }
+
+    fn binary(operator: TokenKind, left: Expression, right: Expression) -> Expression {
+        Expression::Binary {
+            operator,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    #[test]
+    fn test_bitwise_precedence() {
+        let expected = binary(
+            TokenKind::Or,
+            binary(
+                TokenKind::Xor,
+                binary(TokenKind::And, identifier("a"), identifier("b")),
+                identifier("c"),
+            ),
+            identifier("d"),
+        );
+        assert_eq!(parse_expression_text("a & b ^ c | d"), expected);
+        assert_eq!(parse_expression_text("(a & b) ^ c | d"), expected);
+
+        let expected = binary(
+            TokenKind::Or,
+            identifier("a"),
+            binary(
+                TokenKind::Xor,
+                identifier("b"),
+                binary(TokenKind::And, identifier("c"), identifier("d")),
+            ),
+        );
+        assert_eq!(parse_expression_text("a | b ^ c & d"), expected);
+    }
+
+    #[test]
+    fn test_logical_precedence() {
+        assert_eq!(
+            parse_expression_text("a || b && c | d"),
+            binary(
+                TokenKind::LogicalOr,
+                identifier("a"),
+                binary(
+                    TokenKind::LogicalAnd,
+                    identifier("b"),
+                    binary(TokenKind::Or, identifier("c"), identifier("d")),
+                ),
+            )
+        );
+    }
+
+    #[test]
+    fn test_shift_precedence() {
+        assert_eq!(
+            parse_expression_text("a << b + c < d >> e"),
+            binary(
+                TokenKind::Less,
+                binary(
+                    TokenKind::ShiftLeft,
+                    identifier("a"),
+                    binary(TokenKind::Plus, identifier("b"), identifier("c")),
+                ),
+                binary(TokenKind::ShiftRight, identifier("d"), identifier("e")),
+            )
+        );
+    }
+
+    #[test]
+    fn test_xor_left_associative() {
+        assert_eq!(
+            parse_expression_text("a ^ b ^ c"),
+            binary(
+                TokenKind::Xor,
+                binary(TokenKind::Xor, identifier("a"), identifier("b")),
+                identifier("c"),
+            )
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_precedence() {
+        assert_eq!(
+            parse_expression_text("-a + b * 2"),
+            binary(
+                TokenKind::Plus,
+                Expression::Unary {
+                    operator: TokenKind::Minus,
+                    operand: Box::new(identifier("a")),
+                },
+                binary(TokenKind::Mul, identifier("b"), integer("2"),),
+            )
+        );
+    }
+
+    fn parse_function_body(input: &str) -> Vec<Statement> {
+        let (tokens, literal_data) = tokenize_text(input).unwrap();
+        let mut program = Program::from_tokens(tokens, literal_data).unwrap();
+        program.functions.remove(0).body.statements
+    }
+
+    #[test]
+    fn test_labeled_loops() {
+        let statements = parse_function_body(
+            "fn f() { 'outer: while a { while b { break 'outer; } continue; break; } }",
+        );
+        assert_eq!(
+            statements,
+            vec![Statement::While {
+                label: Some("outer".to_string()),
+                condition: identifier("a"),
+                body: block(vec![
+                    Statement::While {
+                        label: None,
+                        condition: identifier("b"),
+                        body: block(vec![Statement::Break {
+                            label: Some("outer".to_string())
+                        }]),
+                    },
+                    Statement::Continue { label: None },
+                    Statement::Break { label: None },
+                ]),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_label_without_loop() {
+        let error = parse_error("fn f() { 'outer: a; }");
+        assert_eq!(error.kind, ParseErrorKind::Expected(vec![TokenKind::While]));
+    }
+
+    #[test]
+    fn test_return_tuple() {
+        let expected = vec![Statement::Return(Some(Expression::Tuple(vec![
+            identifier("a"),
+            identifier("b"),
+        ])))];
+        assert_eq!(parse_function_body("fn f() { return (a, b); }"), expected);
+        assert_eq!(parse_function_body("fn f() { return a, b; }"), expected);
+        assert_eq!(parse_function_body("fn f() { return (a, b,); }"), expected);
+    }
+
+    #[test]
+    fn test_return_statement() {
+        assert_eq!(
+            parse_function_body("fn f() { return; return x + 1; }"),
+            vec![
+                Statement::Return(None),
+                Statement::Return(Some(binary(TokenKind::Plus, identifier("x"), integer("1")))),
+            ]
+        );
+
+        // without a `;` at the end of a block, a `return` is the block's value
+        for (input, value) in [
+            ("fn f() { return }", None),
+            ("fn f() { return x }", Some(identifier("x"))),
+            (
+                "fn f() { return a, b }",
+                Some(Expression::Tuple(vec![identifier("a"), identifier("b")])),
+            ),
+        ] {
+            let (tokens, literal_data) = tokenize_text(input).unwrap();
+            let mut program = Program::from_tokens(tokens, literal_data).unwrap();
+            let body = program.functions.remove(0).body;
+            assert!(body.statements.is_empty(), "{input}");
+            assert_eq!(
+                body.value,
+                Some(Box::new(Expression::Return(value.map(Box::new)))),
+                "{input}"
+            );
+        }
+        assert_eq!(
+            parse_expression_text("if c { return 1 }"),
+            Expression::If {
+                condition: Box::new(identifier("c")),
+                body: CodeBlock {
+                    statements: Vec::new(),
+                    value: Some(Box::new(Expression::Return(Some(Box::new(integer("1")))))),
+                },
+                else_branch: None,
+            }
+        );
+
+        let error = parse_error("fn f() { return x y }");
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::Expected(vec![TokenKind::EndOfStatement])
+        );
+    }
+
+    #[test]
+    fn test_return_parenthesized() {
+        assert_eq!(
+            parse_function_body("fn f() { return (a); return; return (a,); }"),
+            vec![
+                Statement::Return(Some(identifier("a"))),
+                Statement::Return(None),
+                Statement::Return(Some(Expression::Tuple(vec![identifier("a")]))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mutable_arguments() {
+        let (tokens, literal_data) = tokenize_text("fn f(mut x: Int, y: Int) {}").unwrap();
+        let mut program = Program::from_tokens(tokens, literal_data).unwrap();
+        assert_eq!(
+            program.functions.remove(0).arguments,
+            vec![
+                Argument {
+                    name: "x".to_string(),
+                    mutable: true,
+                    argument_type: named("Int"),
+                },
+                Argument {
+                    name: "y".to_string(),
+                    mutable: false,
+                    argument_type: named("Int"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mutable_struct_field() {
+        let error = parse_error("struct S { mut x: Int }");
+        assert_eq!(error.token().map(Token::kind), Some(TokenKind::Mutable));
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::Expected(vec![TokenKind::Identifier, TokenKind::CloseBraces])
+        );
+        assert_eq!(
+            parse_error("struct S { x: Int, mut y: Int }").kind,
+            ParseErrorKind::Expected(vec![TokenKind::Identifier])
+        );
+    }
+
+    /// The first error of parsing `input`.
+    fn parse_error(input: &str) -> ParseError {
+        let (tokens, literal_data) = tokenize_text(input).unwrap();
+        Program::from_tokens(tokens, literal_data)
+            .unwrap_err()
+            .remove(0)
+    }
+
+    #[test]
+    fn test_mismatched_brackets() {
+        for (input, opened, column, found) in [
+            (
+                "fn f(] {}",
+                TokenKind::OpenParentheses,
+                5,
+                TokenKind::CloseBrackets,
+            ),
+            (
+                "fn f(x: i64 }",
+                TokenKind::OpenParentheses,
+                5,
+                TokenKind::CloseBraces,
+            ),
+            (
+                "fn f(x: i64) { a; ) }",
+                TokenKind::OpenBraces,
+                14,
+                TokenKind::CloseParentheses,
+            ),
+            (
+                "struct S { x: [i64) }",
+                TokenKind::OpenBrackets,
+                15,
+                TokenKind::CloseParentheses,
+            ),
+            (
+                "fn f() { (a, b]; }",
+                TokenKind::OpenParentheses,
+                10,
+                TokenKind::CloseBrackets,
+            ),
+        ] {
+            assert_eq!(
+                parse_error(input).kind,
+                ParseErrorKind::MismatchedBracket {
+                    opened,
+                    open_location: Location { line: 1, column },
+                    found,
+                },
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_unexpected_token_in_brackets() {
+        assert_eq!(
+            parse_error("fn f(x: i64; {}").kind,
+            ParseErrorKind::Expected(vec![TokenKind::Comma, TokenKind::CloseParentheses])
+        );
+    }
+
+    fn integer(digits: &str) -> Expression {
+        Expression::IntegerLiteral {
+            digits: digits.to_string(),
+            suffix: None,
+        }
+    }
+
+    fn field(base: Expression, field: &str) -> Expression {
+        Expression::Field {
+            base: Box::new(base),
+            field: field.to_string(),
+        }
+    }
+
+    fn index(base: Expression, index: Expression) -> Expression {
+        Expression::Index {
+            base: Box::new(base),
+            index: Box::new(index),
+        }
+    }
+
+    #[test]
+    fn test_assignment_targets() {
+        assert_eq!(
+            parse_function_body("fn f() { a[0] = 1; p.x = 2; m[k].field = v; *r = 3; }"),
+            vec![
+                Statement::Assign {
+                    target: index(identifier("a"), integer("0")),
+                    value: integer("1"),
+                },
+                Statement::Assign {
+                    target: field(identifier("p"), "x"),
+                    value: integer("2"),
+                },
+                Statement::Assign {
+                    target: field(index(identifier("m"), identifier("k")), "field"),
+                    value: identifier("v"),
+                },
+                Statement::Assign {
+                    target: Expression::Unary {
+                        operator: TokenKind::Mul,
+                        operand: Box::new(identifier("r")),
+                    },
+                    value: integer("3"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_assignment_target() {
+        for input in [
+            "fn f() { f() = 1; }",
+            "fn f() { f().x = 1; }",
+            "fn f() { a + b = 1; }",
+        ] {
+            let error = parse_error(input);
+            assert_eq!(error.kind, ParseErrorKind::InvalidAssignmentTarget);
+            assert_eq!(error.token.map(|t| t.kind()), Some(TokenKind::Assign));
+        }
+    }
+
+    fn not(operand: Expression) -> Expression {
+        Expression::Unary {
+            operator: TokenKind::Not,
+            operand: Box::new(operand),
+        }
+    }
+
+    #[test]
+    fn test_prefix_not() {
+        assert_eq!(parse_expression_text("!x"), not(identifier("x")));
+        assert_eq!(parse_expression_text("!!x"), not(not(identifier("x"))));
+        assert_eq!(
+            parse_expression_text("!a.b"),
+            not(field(identifier("a"), "b"))
+        );
+    }
+
+    #[test]
+    fn test_double_reference() {
+        let reference = |operand| Expression::Unary {
+            operator: TokenKind::And,
+            operand: Box::new(operand),
+        };
+        assert_eq!(
+            parse_expression_text("&&x"),
+            reference(reference(identifier("x")))
+        );
+        assert_eq!(
+            parse_expression_text("a && &&b"),
+            binary(
+                TokenKind::LogicalAnd,
+                identifier("a"),
+                reference(reference(identifier("b")))
+            )
+        );
+
+        let fields = parse_struct_fields("struct A { a: &&Int }");
+        assert_eq!(
+            fields[0].1,
+            Type::Reference(Box::new(Type::Reference(Box::new(named("Int")))))
+        );
+    }
+
+    #[test]
+    fn test_dangling_not() {
+        let error = parse_error("fn f() { x = !; }");
+        assert_eq!(error.kind, ParseErrorKind::MissingOperand);
+        let token = error.token.unwrap();
+        assert_eq!(token.kind(), TokenKind::Not);
+        assert_eq!(
+            token.location(),
+            Location {
+                line: 1,
+                column: 14
+            }
+        );
+    }
+
+    fn call(callee: Expression, arguments: Vec<Expression>) -> Expression {
+        Expression::Call {
+            callee: Box::new(callee),
+            arguments,
+        }
+    }
+
+    #[test]
+    fn test_chained_postfix() {
+        assert_eq!(
+            parse_expression_text("a.b().c[0]"),
+            index(
+                field(call(field(identifier("a"), "b"), Vec::new()), "c"),
+                integer("0")
+            )
+        );
+        assert_eq!(
+            parse_expression_text("f(x)[1](y, z)"),
+            call(
+                index(call(identifier("f"), vec![identifier("x")]), integer("1")),
+                vec![identifier("y"), identifier("z")]
+            )
+        );
+    }
+
+    fn parse_with_attributes(input: &str) -> Result<Program, ParseError> {
+        let options = TokenizerOptions {
+            hash: HashRole::Token,
+            ..TokenizerOptions::default()
+        };
+        let (tokens, literal_data) = tokenize_text_with_options(input, &options).unwrap();
+        Program::from_tokens(tokens, literal_data).map_err(|mut errors| errors.remove(0))
+    }
+
+    #[test]
+    fn test_attributes() {
+        let mut program = parse_with_attributes("#[inline] fn f(){}").unwrap();
+        let attributes = program.functions.remove(0).attributes;
+        assert_eq!(
+            attributes,
+            vec![Attribute {
+                name: "inline".to_string(),
+                location: Location { line: 1, column: 3 },
+                args: Vec::new(),
+            }]
+        );
+
+        let mut program = parse_with_attributes("#[a] #[b(1, x)] struct S {}\nfn f() {}").unwrap();
+        let attributes = program.structs.remove(0).attributes;
+        let names: Vec<_> = attributes.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, &["a", "b"]);
+        assert_eq!(attributes[1].args, vec![integer("1"), identifier("x")]);
+        assert!(program.functions[0].attributes.is_empty());
+    }
+
+    #[test]
+    fn test_malformed_attributes() {
+        let error = parse_with_attributes("#[").unwrap_err();
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::Expected(vec![TokenKind::Identifier])
+        );
+        let token = error.token.unwrap();
+        assert_eq!(token.kind(), TokenKind::EndOfFile);
+        assert_eq!(token.location(), Location { line: 1, column: 3 });
+
+        let error = parse_with_attributes("#[inline fn f() {}").unwrap_err();
+        assert_eq!(
+            error.token().map(Token::kind),
+            Some(TokenKind::FunctionDefinition)
+        );
+
+        assert!(parse_with_attributes("fn f() {} #[inline]").is_err());
+    }
+
+    #[test]
+    fn test_imports() {
+        let (tokens, literal_data) = tokenize_text("use std::io;\nuse fmt;").unwrap();
+        let program = Program::from_tokens(tokens, literal_data).unwrap();
+        assert_eq!(
+            program.imports,
+            vec![
+                ImportPath::Single {
+                    segments: vec!["std".to_string(), "io".to_string()],
+                    location: Location { line: 1, column: 5 },
+                },
+                ImportPath::Single {
+                    segments: vec!["fmt".to_string()],
+                    location: Location { line: 2, column: 5 },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_imports() {
+        assert_eq!(
+            parse_error("use;").kind,
+            ParseErrorKind::Expected(vec![TokenKind::Identifier])
+        );
+        assert_eq!(
+            parse_error("use std::io fn f() {}").kind,
+            ParseErrorKind::Expected(vec![TokenKind::PathSep, TokenKind::EndOfStatement])
+        );
+        assert_eq!(
+            parse_error("use std::;").kind,
+            ParseErrorKind::Expected(vec![
+                TokenKind::Identifier,
+                TokenKind::Mul,
+                TokenKind::OpenBraces
+            ])
+        );
+    }
+
+    fn strings(strings: &[&str]) -> Vec<String> {
+        strings.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_glob_and_group_imports() {
+        let (tokens, literal_data) = tokenize_text("use a::b::*;\nuse a::{b, c::d,};").unwrap();
+        let program = Program::from_tokens(tokens, literal_data).unwrap();
+        assert_eq!(
+            program.imports,
+            vec![
+                ImportPath::Glob {
+                    prefix: strings(&["a", "b"]),
+                    location: Location { line: 1, column: 5 },
+                },
+                ImportPath::Group {
+                    prefix: strings(&["a"]),
+                    members: vec![strings(&["b"]), strings(&["c", "d"])],
+                    location: Location { line: 2, column: 5 },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_glob_not_last() {
+        let error = parse_error("use a::*::b;");
+        assert_eq!(error.token().map(Token::kind), Some(TokenKind::PathSep));
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::Expected(vec![TokenKind::EndOfStatement])
+        );
+        assert_eq!(
+            parse_error("use a;b").kind,
+            ParseErrorKind::Expected(vec![TokenKind::FunctionDefinition, TokenKind::Struct])
+        );
+    }
+
+    fn block(statements: Vec<Statement>) -> CodeBlock {
+        CodeBlock {
+            statements,
+            value: None,
+        }
+    }
+
+    #[test]
+    fn test_plain_if_and_if_else() {
+        let plain = Expression::If {
+            condition: Box::new(identifier("a")),
+            body: block(vec![Statement::Expression(identifier("x"))]),
+            else_branch: None,
+        };
+        assert_eq!(parse_expression_text("if a { x; }"), plain);
+        // parentheses aren't needed but are allowed
+        assert_eq!(parse_expression_text("if (a) { x; }"), plain);
+
+        assert_eq!(
+            parse_expression_text("if a < b { x; } else { y; }"),
+            Expression::If {
+                condition: Box::new(binary(TokenKind::Less, identifier("a"), identifier("b"))),
+                body: block(vec![Statement::Expression(identifier("x"))]),
+                else_branch: Some(ElseBranch::Block(block(vec![Statement::Expression(
+                    identifier("y")
+                )]))),
+            }
+        );
+    }
+
+    #[test]
+    fn test_if_else() {
+        let statements = parse_function_body("fn f() { if a { x; } else if b {} else { y; }; }");
+        assert_eq!(
+            statements,
+            vec![Statement::Expression(Expression::If {
+                condition: Box::new(identifier("a")),
+                body: block(vec![Statement::Expression(identifier("x"))]),
+                else_branch: Some(ElseBranch::If(Box::new(Expression::If {
+                    condition: Box::new(identifier("b")),
+                    body: block(Vec::new()),
+                    else_branch: Some(ElseBranch::Block(block(vec![Statement::Expression(
+                        identifier("y")
+                    )]))),
+                }))),
+            })]
+        );
+    }
+
+    #[test]
+    fn test_assignment_in_condition() {
+        for (input, column) in [
+            ("fn f() { if a = b {} }", 15),
+            ("fn f() { while a = b {} }", 18),
+        ] {
+            let error = parse_error(input);
+            assert_eq!(error.kind, ParseErrorKind::AssignmentInCondition);
+            let token = error.token.unwrap();
+            assert_eq!(token.kind(), TokenKind::Assign);
+            assert_eq!(token.location(), Location { line: 1, column });
+        }
+    }
+
+    #[test]
+    fn test_empty_statements() {
+        let if_a = || {
+            Statement::Expression(Expression::If {
+                condition: Box::new(identifier("a")),
+                body: block(Vec::new()),
+                else_branch: None,
+            })
+        };
+        assert_eq!(parse_function_body("fn f() { if a {}; }"), vec![if_a()]);
+        assert_eq!(
+            parse_function_body("fn f() { if a {};; if a {} ; }"),
+            vec![if_a(), if_a()]
+        );
+        assert_eq!(
+            parse_function_body("fn f() { { ; }; ;; }"),
+            vec![Statement::Expression(Expression::Block(block(Vec::new())))]
+        );
+    }
+
+    #[test]
+    fn test_return_expression() {
+        let statements = parse_function_body("fn f() { a := if c { return; } else { 1 }; }");
+        assert_eq!(
+            statements,
+            vec![Statement::Let {
+                name: "a".to_string(),
+                ty: None,
+                value: Some(Expression::If {
+                    condition: Box::new(identifier("c")),
+                    body: block(vec![Statement::Return(None)]),
+                    else_branch: Some(ElseBranch::Block(CodeBlock {
+                        statements: Vec::new(),
+                        value: Some(Box::new(integer("1"))),
+                    })),
+                }),
+                mutable: false,
+            }]
+        );
+
+        assert_eq!(
+            parse_expression_text("y | return"),
+            binary(TokenKind::Or, identifier("y"), Expression::Return(None))
+        );
+        assert_eq!(
+            parse_expression_text("y | return x + 1"),
+            binary(
+                TokenKind::Or,
+                identifier("y"),
+                Expression::Return(Some(Box::new(binary(
+                    TokenKind::Plus,
+                    identifier("x"),
+                    integer("1")
+                ))))
+            )
+        );
+    }
+
+    #[test]
+    fn test_type_params() {
+        let (tokens, literal_data) =
+            tokenize_text("struct Box<T> { value: T }\nfn id<T>(x: T) -> T {}\nfn f<>() {}")
+                .unwrap();
+        let program = Program::from_tokens(tokens, literal_data).unwrap();
+        assert_eq!(program.structs[0].type_params, strings(&["T"]));
+        assert_eq!(
+            program.structs[0].fields,
+            vec![("value".to_string(), named("T"))]
+        );
+        assert_eq!(program.functions[0].type_params, strings(&["T"]));
+        assert_eq!(program.functions[0].return_type, named("T"));
+        assert!(program.functions[1].type_params.is_empty());
+
+        assert_eq!(
+            parse_error("struct S<T U> {}").kind,
+            ParseErrorKind::Expected(vec![TokenKind::Comma, TokenKind::Greater])
+        );
+    }
+
+    fn struct_literal(name: &str, fields: Vec<(&str, Expression)>) -> Expression {
+        Expression::StructLiteral {
+            name: name.to_string(),
+            fields: fields
+                .into_iter()
+                .map(|(field, value)| (field.to_string(), value))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_struct_literals() {
+        assert_eq!(
+            parse_expression_text("Outer { inner: Inner { x: 1 }, total: a + b, }"),
+            struct_literal(
+                "Outer",
+                vec![
+                    ("inner", struct_literal("Inner", vec![("x", integer("1"))])),
+                    (
+                        "total",
+                        binary(TokenKind::Plus, identifier("a"), identifier("b"))
+                    ),
+                ]
+            )
+        );
+        assert_eq!(
+            parse_expression_text("f(Empty {})"),
+            Expression::Call {
+                callee: Box::new(identifier("f")),
+                arguments: vec![struct_literal("Empty", Vec::new())],
+            }
+        );
+    }
+
+    #[test]
+    fn test_struct_literal_in_condition() {
+        let statements = parse_function_body("fn f() { if a == b { x; }; }");
+        assert_eq!(
+            statements,
+            vec![Statement::Expression(Expression::If {
+                condition: Box::new(binary(TokenKind::Equal, identifier("a"), identifier("b"))),
+                body: block(vec![Statement::Expression(identifier("x"))]),
+                else_branch: None,
+            })]
+        );
+
+        let statements = parse_function_body("fn f() { while a == (B { x: 1 }) {} }");
+        assert_eq!(
+            statements,
+            vec![Statement::While {
+                label: None,
+                condition: binary(
+                    TokenKind::Equal,
+                    identifier("a"),
+                    struct_literal("B", vec![("x", integer("1"))])
+                ),
+                body: block(Vec::new()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_else_without_if() {
+        for (input, column) in [
+            ("fn f() { else { } }", 10),
+            ("fn f() { if a {} x; else {} }", 21),
+            ("fn f() { if a {} else {} else {} }", 26),
+        ] {
+            let error = parse_error(input);
+            assert_eq!(error.kind, ParseErrorKind::ElseWithoutIf);
+            assert_eq!(
+                error.token.unwrap().location(),
+                Location { line: 1, column }
+            );
+        }
+    }
+
+    fn let_statement(name: &str, ty: Option<Type>, value: Option<Expression>) -> Statement {
+        Statement::Let {
+            name: name.to_string(),
+            ty,
+            value,
+            mutable: false,
+        }
+    }
+
+    #[test]
+    fn test_let() {
+        let statements = parse_function_body(
+            "fn f() { let a: Int = 1; let b = 2; let c: Int; let mut d = 3; e := 4; }",
+        );
+        assert_eq!(
+            statements,
+            vec![
+                let_statement("a", Some(named("Int")), Some(integer("1"))),
+                let_statement("b", None, Some(integer("2"))),
+                let_statement("c", Some(named("Int")), None),
+                Statement::Let {
+                    name: "d".to_string(),
+                    ty: None,
+                    value: Some(integer("3")),
+                    mutable: true,
+                },
+                let_statement("e", None, Some(integer("4"))),
+            ]
+        );
+
+        assert_eq!(
+            parse_error("fn f() { let x 1; }").kind,
+            ParseErrorKind::Expected(vec![
+                TokenKind::FieldTypeSeparator,
+                TokenKind::Assign,
+                TokenKind::EndOfStatement
+            ])
+        );
+        assert_eq!(
+            parse_error("fn f() { let x: Int 1; }").kind,
+            ParseErrorKind::Expected(vec![TokenKind::Assign, TokenKind::EndOfStatement])
+        );
+    }
+
+    #[test]
+    fn test_unit_return_type() {
+        let (tokens, literal_data) =
+            tokenize_text("fn f() {}\nfn g() -> () {}\nfn h() -> Int {}").unwrap();
+        let program = Program::from_tokens(tokens, literal_data).unwrap();
+        let return_types: Vec<_> = program
+            .functions
+            .iter()
+            .map(|function| &function.return_type)
+            .collect();
+        assert_eq!(return_types, [&Type::Unit, &Type::Unit, &named("Int")]);
+    }
+
+    #[test]
+    fn test_match() {
+        assert_eq!(
+            parse_expression_text("match x { 1 => a, _ => b, }"),
+            Expression::Match {
+                scrutinee: Box::new(identifier("x")),
+                arms: vec![
+                    (
+                        Pattern::IntegerLiteral {
+                            digits: "1".to_string(),
+                            suffix: None
+                        },
+                        identifier("a")
+                    ),
+                    (Pattern::Wildcard, identifier("b")),
+                ]
+            }
+        );
+
+        assert_eq!(
+            parse_function_body("fn f() { match 'a' { 'a' => {} } x; }").len(),
+            2
+        );
+
+        assert_eq!(
+            parse_error("fn f() { match x { 1 a } }").kind,
+            ParseErrorKind::Expected(vec![TokenKind::FatArrow])
+        );
+    }
+
+    #[test]
+    fn test_implicit_return() {
+        let (tokens, literal_data) =
+            tokenize_text("fn f() -> Int { 1 }\nfn g() -> Int { 1; }").unwrap();
+        let program = Program::from_tokens(tokens, literal_data).unwrap();
+
+        let implicit = &program.functions[0].body;
+        assert!(implicit.statements.is_empty());
+        assert_eq!(implicit.value, Some(Box::new(integer("1"))));
+
+        let statement = &program.functions[1].body;
+        assert_eq!(
+            statement.statements,
+            vec![Statement::Expression(integer("1"))]
+        );
+        assert_eq!(statement.value, None);
+
+        // block-like expressions at the end are values too, unless a `;`
+        // follows them
+        for input in [
+            "fn f() -> i64 { if c { 1 } else { 2 } }",
+            "fn f() -> i64 { match x { 1 => 2, _ => 3 } }",
+            "fn f() -> i64 { { 1 } }",
+            "fn f() -> i64 { a; while c {} if c { 1 } else { 2 } }",
+        ] {
+            let (tokens, literal_data) = tokenize_text(input).unwrap();
+            let body = &Program::from_tokens(tokens, literal_data)
+                .unwrap()
+                .functions[0]
+                .body;
+            assert!(
+                matches!(
+                    body.value.as_deref(),
+                    Some(Expression::If { .. } | Expression::Match { .. } | Expression::Block(_))
+                ),
+                "{input}"
+            );
+        }
+        let body = parse_function_body("fn f() { { 1 } { 2 }; }");
+        assert_eq!(body.len(), 2);
+        assert!(body
+            .iter()
+            .all(|statement| matches!(statement, Statement::Expression(Expression::Block(_)))));
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let statements = parse_function_body("fn f() { x += 1; a.b %= 2; y = 3; }");
+        assert_eq!(
+            statements,
+            vec![
+                Statement::CompoundAssign {
+                    operator: TokenKind::Plus,
+                    target: identifier("x"),
+                    value: integer("1"),
+                },
+                Statement::CompoundAssign {
+                    operator: TokenKind::Rem,
+                    target: field(identifier("a"), "b"),
+                    value: integer("2"),
+                },
+                Statement::Assign {
+                    target: identifier("y"),
+                    value: integer("3"),
+                },
+            ]
+        );
+
+        let error = parse_error("fn f() { 1 -= x; }");
+        assert_eq!(error.kind, ParseErrorKind::InvalidAssignmentTarget);
+        assert_eq!(error.token.map(|t| t.kind()), Some(TokenKind::MinusAssign));
+    }
+
+    #[test]
+    fn test_code_blocks() {
+        assert!(parse_function_body("fn f() { }").is_empty());
+
+        assert_eq!(
+            parse_function_body("fn f() { a; { b; {} } c; }"),
+            vec![
+                Statement::Expression(identifier("a")),
+                Statement::Expression(Expression::Block(CodeBlock {
+                    statements: vec![Statement::Expression(identifier("b"))],
+                    value: Some(Box::new(Expression::Block(block(Vec::new())))),
+                })),
+                Statement::Expression(identifier("c")),
+            ]
+        );
+
+        let error = parse_error("fn f() { { a; }");
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::Expected(vec![TokenKind::CloseBraces])
+        );
+        assert_eq!(error.token.unwrap().kind(), TokenKind::EndOfFile);
+    }
+
+    #[test]
+    fn test_definition_assignment_and_expression_statements() {
+        assert_eq!(
+            parse_function_body("fn f() { x := 5; y = x + 1; g(y); }"),
+            vec![
+                let_statement("x", None, Some(integer("5"))),
+                Statement::Assign {
+                    target: identifier("y"),
+                    value: binary(TokenKind::Plus, identifier("x"), integer("1")),
+                },
+                Statement::Expression(call(identifier("g"), vec![identifier("y")])),
+            ]
+        );
+
+        let error = parse_error("fn f() { x := 5 }");
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::Expected(vec![TokenKind::EndOfStatement])
+        );
+        assert_eq!(error.token.unwrap().kind(), TokenKind::CloseBraces);
+    }
+
+    #[test]
+    fn test_while() {
+        assert_eq!(
+            parse_function_body("fn f() { while x < 10 { x = x + 1; } }"),
+            vec![Statement::While {
+                label: None,
+                condition: binary(TokenKind::Less, identifier("x"), integer("10")),
+                body: block(vec![Statement::Assign {
+                    target: identifier("x"),
+                    value: binary(TokenKind::Plus, identifier("x"), integer("1")),
+                }]),
+            }]
+        );
+
+        for input in ["fn f() { while { x; } }", "fn f() { if {} }"] {
+            let error = parse_error(input);
+            let ParseErrorKind::Expected(expected) = error.kind else {
+                panic!("{input}");
+            };
+            assert!(expected.contains(&TokenKind::Identifier));
+            assert!(!expected.contains(&TokenKind::OpenBraces));
+            assert_eq!(error.token.unwrap().kind(), TokenKind::OpenBraces);
+        }
+    }
+
+    #[test]
+    fn test_bool_literals() {
+        assert_eq!(
+            parse_expression_text("true && !false"),
+            binary(
+                TokenKind::LogicalAnd,
+                Expression::BoolLiteral(true),
+                not(Expression::BoolLiteral(false))
+            )
+        );
+    }
+
+    #[test]
+    fn test_calls() {
+        assert_eq!(
+            parse_expression_text("f()"),
+            call(identifier("f"), Vec::new())
+        );
+        assert_eq!(
+            parse_expression_text("f(1)"),
+            call(identifier("f"), vec![integer("1")])
+        );
+        assert_eq!(
+            parse_expression_text("f(a, g(b),)"),
+            call(
+                identifier("f"),
+                vec![
+                    identifier("a"),
+                    call(identifier("g"), vec![identifier("b")])
+                ]
+            )
+        );
+
+        let error = parse_error("fn f() { f(, a); }");
+        let ParseErrorKind::Expected(expected) = error.kind else {
+            panic!();
+        };
+        assert!(expected.contains(&TokenKind::Identifier));
+        assert!(expected.contains(&TokenKind::CloseParentheses));
+    }
+
+    #[test]
+    fn test_field_access() {
+        assert_eq!(
+            parse_expression_text("point.x"),
+            field(identifier("point"), "x")
+        );
+        assert_eq!(
+            parse_expression_text("a.b.c"),
+            field(field(identifier("a"), "b"), "c")
+        );
+        // the `.` of a float literal is part of the literal
+        assert_eq!(
+            parse_expression_text("a.b * 1.5"),
+            binary(
+                TokenKind::Mul,
+                field(identifier("a"), "b"),
+                Expression::FloatLiteral("1.5".to_string())
+            )
+        );
+
+        assert_eq!(
+            parse_error("fn f() { a.; }").kind,
+            ParseErrorKind::Expected(vec![TokenKind::Identifier])
+        );
+    }
+
+    #[test]
+    fn test_missing_function_body() {
+        let error = parse_error("fn f()\nstruct");
+        let ParseErrorKind::Expected(expected) = &error.kind else {
+            panic!();
+        };
+        assert!(expected.contains(&TokenKind::RightArrow));
+        assert!(expected.contains(&TokenKind::OpenBraces));
+        assert_eq!(error.location(), Some(Location { line: 2, column: 1 }));
+
+        let error = parse_error("fn f()");
+        assert_eq!(error.location(), Some(Location { line: 1, column: 7 }));
+    }
+
+    #[test]
+    fn test_display_struct() {
+        let program = parse_with_attributes(
+            "#[derive(Token)] struct Pair<T> { first: &T, second: [Vec(T)], third: (u8, i64), }",
+        )
+        .unwrap();
+        assert_eq!(
+            program.structs[0].to_string(),
+            "#[derive(Token)]\n\
+             struct Pair<T> {\n    first: &T,\n    second: [Vec(T)],\n    third: (u8, i64),\n}"
+        );
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let input = r#"
+            use std::{io, fmt::Display};
+            struct Empty {}
+            fn main(mut a: i64, b: &str) -> (i64, bool) {
+                let mut x: i64 = (a + 1) * -b.len();
+                y := S { f: 1, g: "a\n\"" };
+                'outer: while x > 0 && !done {
+                    x -= 1;
+                    if x == 5 { break 'outer; } else if x < 2 { continue; } else {}
+                }
+                match x { 0u8 => 'c', _ => '\'' };
+                (if a { 1 } else { 2 }) + 255u8;
+                ({ x }).len();
+                return a - (b - c), f(x)[0].y;
+                (1,)
+            }
+            fn unit() {}
+        "#;
+        let (tokens, literal_data) = tokenize_text(input).unwrap();
+        let program = Program::from_tokens(tokens, literal_data).unwrap();
+        let printed = program.to_string();
+
+        let (tokens, literal_data) = tokenize_text(&printed).unwrap();
+        let reparsed = Program::from_tokens(tokens, literal_data).unwrap();
+        assert_eq!(reparsed.to_string(), printed);
+        assert_eq!(reparsed.functions[0].body, program.functions[0].body);
+        assert!(printed.contains("let mut x: i64 = (a + 1) * -b.len();\n"));
+        assert!(printed.contains("return (a - (b - c), f(x)[0].y);"));
+        assert!(printed.contains("    0u8 => 'c',\n"));
+        assert!(
+            printed.contains("    (if a {\n        1\n    } else {\n        2\n    }) + 255u8;\n")
+        );
+        assert!(printed.contains("    ({\n        x\n    }).len();\n"));
+        assert!(printed
+            .contains("        } else if x < 2 {\n            continue;\n        } else {}\n"));
+        assert!(printed.ends_with("\n\nfn unit() {}\n"));
+    }
+
+    #[test]
+    fn test_display_expressions() {
+        for (input, printed) in [
+            ("a - (b - c)", "a - (b - c)"),
+            ("(a - b) - c", "a - b - c"),
+            ("(a || b) && c", "(a || b) && c"),
+            ("-(a + b)", "-(a + b)"),
+            ("(*a).b", "(*a).b"),
+            ("&&x", "&&x"),
+            ("((a))", "a"),
+            ("()", "()"),
+            ("(a, b,)", "(a, b)"),
+        ] {
+            assert_eq!(parse_expression_text(input).to_string(), printed, "{input}");
+        }
+    }
+
+    fn parse_errors(input: &str) -> Vec<ParseError> {
+        let (tokens, literal_data) = tokenize_text(input).unwrap();
+        Program::from_tokens(tokens, literal_data).unwrap_err()
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        let errors = parse_errors(
+            "fn a() { x := ; }\n\
+             struct Fine { x: i64 }\n\
+             fn b() { y := 1 + * ; }\n\
+             fn c() {}",
+        );
+        let locations: Vec<_> = errors.iter().map(|error| error.location()).collect();
+        assert_eq!(
+            locations,
+            vec![
+                Some(Location {
+                    line: 1,
+                    column: 15
+                }),
+                Some(Location {
+                    line: 3,
+                    column: 19
+                }),
+            ]
+        );
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::Expected(EXPRESSION_START.to_vec())
+        );
+        assert_eq!(errors[1].kind, ParseErrorKind::MissingOperand);
+    }
+
+    #[test]
+    fn test_error_recovery_resumes_at_item() {
+        // the `fn` that ends the broken argument list starts the next function
+        let errors = parse_errors("fn a( fn b( {}");
+        assert_eq!(errors.len(), 2);
+        let locations: Vec<_> = errors.iter().map(|error| error.location()).collect();
+        assert_eq!(
+            locations,
+            vec![
+                Some(Location { line: 1, column: 7 }),
+                Some(Location {
+                    line: 1,
+                    column: 13
+                })
+            ]
+        );
+        assert_eq!(
+            errors[0].token().map(Token::kind),
+            Some(TokenKind::FunctionDefinition)
+        );
+
+        // and garbage outside of items is one error per token that can't
+        // start an item, without looping
+        let errors = parse_errors("} ; fn f() {} )");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[1].location(),
+            Some(Location {
+                line: 1,
+                column: 15
+            })
+        );
+
+        let errors = parse_errors("fn f() {");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].token().map(Token::kind),
+            Some(TokenKind::EndOfFile)
+        );
+    }
+
+    fn parse_script(input: &str) -> Result<AbstractSyntaxTree, ParseError> {
+        let (tokens, literal_data) = tokenize_text(input).unwrap();
+        AbstractSyntaxTree::from_tokens(tokens, literal_data)
+    }
+
+    #[test]
+    fn test_script() {
+        let ast = parse_script("x := 1; y := 2;").unwrap();
+        assert_eq!(
+            ast.statements,
+            vec![
+                Statement::Let {
+                    name: "x".to_string(),
+                    ty: None,
+                    value: Some(integer("1")),
+                    mutable: false,
+                },
+                Statement::Let {
+                    name: "y".to_string(),
+                    ty: None,
+                    value: Some(integer("2")),
+                    mutable: false,
+                },
+            ]
+        );
+
+        let ast = parse_script(";; while x { x -= 1; } if x {} f(x);").unwrap();
+        assert_eq!(ast.statements.len(), 3);
+        assert!(parse_script("").unwrap().statements.is_empty());
+    }
+
+    #[test]
+    fn test_script_errors() {
+        let error = parse_script("x := 1").unwrap_err();
+        assert_eq!(error.token().map(Token::kind), Some(TokenKind::EndOfFile));
+
+        let error = parse_script("x }").unwrap_err();
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::Expected(vec![TokenKind::EndOfStatement])
+        );
+        assert_eq!(error.token().map(Token::kind), Some(TokenKind::CloseBraces));
+    }
+
+    #[test]
+    fn test_keyword_as_definition_name() {
+        for (input, keyword) in [
+            ("struct struct {}", TokenKind::Struct),
+            ("fn fn() {}", TokenKind::FunctionDefinition),
+            ("struct use { x: i64 }", TokenKind::Use),
+        ] {
+            let errors = parse_errors(input);
+            assert_eq!(errors.len(), 1, "{input}");
+            assert_eq!(
+                errors[0].kind,
+                ParseErrorKind::Expected(vec![TokenKind::Identifier])
+            );
+            let token = errors[0].token().unwrap();
+            assert_eq!(token.kind(), keyword);
+            assert_eq!(token.location().column, input.find(' ').unwrap() + 2);
+        }
+
+        // a keyword after the name still starts the next item
+        assert_eq!(parse_errors("struct S fn f() {}").len(), 1);
+        assert_eq!(parse_errors("fn f( fn g() {}").len(), 1);
+    }
+
+    #[test]
+    fn test_mutable_definitions() {
+        let statements = parse_function_body("fn f() { mut a := 1; b := 2; }");
+        assert_eq!(
+            statements,
+            vec![
+                Statement::Let {
+                    name: "a".to_string(),
+                    ty: None,
+                    value: Some(integer("1")),
+                    mutable: true,
+                },
+                let_statement("b", None, Some(integer("2"))),
+            ]
+        );
+
+        let (tokens, literal_data) = tokenize_text("fn f(x: Int) {}").unwrap();
+        let program = Program::from_tokens(tokens, literal_data).unwrap();
+        assert!(!program.functions[0].arguments[0].mutable);
+
+        for input in [
+            "fn f() { mut mut a := 1; }",
+            "fn f() { let mut mut a = 1; }",
+            "fn f(mut mut x: Int) {}",
+        ] {
+            let error = parse_error(input);
+            assert_eq!(
+                error.kind,
+                ParseErrorKind::Expected(vec![TokenKind::Identifier]),
+                "{input}"
+            );
+            assert_eq!(error.token().map(Token::kind), Some(TokenKind::Mutable));
+        }
+
+        // a plain assignment can't make a variable mutable
+        assert_eq!(
+            parse_error("fn f() { mut a = 1; }").kind,
+            ParseErrorKind::Expected(vec![TokenKind::DefineVar])
+        );
+    }
+
+    #[test]
+    fn test_parse_example() {
+        let (tokens, literal_data) = tokenize_text(include_str!("../example.txt")).unwrap();
+        let program = Program::from_tokens(tokens, literal_data).unwrap();
+        assert_eq!(program.functions.len(), 2);
+        assert_eq!(program.structs.len(), 1);
     }
 }
@@ -0,0 +1,186 @@
+use crate::ast::Program;
+use crate::diagnostic::Diagnostic;
+use crate::resolve::resolve_names;
+use crate::token::{Span, Token, TokenKind};
+use crate::tokenizer::{tokenize_text_recovering, TokenizerOptions, TokenizingError};
+
+/// Runs every stage of compilation over `source`, collecting the diagnostics
+/// of all stages instead of stopping at the first problem.
+///
+/// A stage that cannot produce a result stops the pipeline, but the stages
+/// before it still report everything they found. Warnings alone don't make
+/// compilation fail.
+pub fn compile(source: &str) -> Result<Program, Vec<Diagnostic>> {
+    match compile_with_diagnostics(source) {
+        (Some(program), diagnostics) if !diagnostics.iter().any(Diagnostic::is_error) => {
+            Ok(program)
+        }
+        (_, diagnostics) => Err(diagnostics),
+    }
+}
+
+/// Like `compile`, but also returns the warnings of a successful compilation.
+/// The program is `None` only if a stage couldn't produce a result, so check
+/// the diagnostics for errors before using it.
+pub fn compile_with_diagnostics(source: &str) -> (Option<Program>, Vec<Diagnostic>) {
+    let options = TokenizerOptions {
+        warn_leading_zeros: true,
+        ..TokenizerOptions::default()
+    };
+    let output = tokenize_text_recovering(source, &options);
+    let tokens = with_error_tokens(output.tokens, &output.errors);
+    let mut diagnostics: Vec<Diagnostic> = output
+        .errors
+        .into_iter()
+        .map(Diagnostic::from)
+        .chain(output.warnings.into_iter().map(Diagnostic::from))
+        .collect();
+
+    let program = match Program::from_tokens(tokens, output.literal_data) {
+        Ok(program) => Some(program),
+        Err(errors) => {
+            // an error at input that failed to tokenize was already reported
+            diagnostics.extend(
+                errors
+                    .into_iter()
+                    .filter(|error| error.token().map(Token::kind) != Some(TokenKind::Error))
+                    .map(Diagnostic::from),
+            );
+            None
+        }
+    };
+
+    if let Some(program) = &program {
+        diagnostics.extend(resolve_names(program).into_iter().map(Diagnostic::from));
+    }
+
+    (program, diagnostics)
+}
+
+/// Puts an `Error` token where each tokenizing error is, so that the parser
+/// sees that something was there instead of reporting the tokens around it.
+fn with_error_tokens(tokens: Vec<Token>, errors: &[TokenizingError]) -> Vec<Token> {
+    let mut errors = errors.iter().peekable();
+    let mut output = Vec::with_capacity(tokens.len() + errors.len());
+    for token in tokens {
+        let location = token.location();
+        while let Some(error) = errors.next_if(|error| {
+            (error.location.line, error.location.column) < (location.line, location.column)
+        }) {
+            let span = Span {
+                start: error.location,
+                end: error.location,
+            };
+            output.push(Token::new(TokenKind::Error, span));
+        }
+        output.push(token);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::Location;
+
+    #[test]
+    fn test_valid_program() {
+        assert!(compile("struct A { x: i64 }\nstruct B { a: A }").is_ok());
+    }
+
+    #[test]
+    fn test_reports_all_stages() {
+        let diagnostics = compile("struct A { x: i64 } @\nstruct B { a: Foo }").unwrap_err();
+        let locations: Vec<_> = diagnostics.iter().map(|d| d.location).collect();
+        assert_eq!(
+            locations,
+            vec![
+                Some(Location {
+                    line: 1,
+                    column: 21
+                }),
+                Some(Location { line: 2, column: 8 }),
+            ]
+        );
+        assert!(diagnostics[0].message.contains("token"));
+        assert!(diagnostics[1].message.contains("Foo"));
+    }
+
+    #[test]
+    fn test_tokenizing_error_in_body() {
+        for (source, code) in [
+            ("fn f() { s := \"a\\q\"; x := y; }", "E0001"),
+            ("fn f() { x := 1 @ 2; y := z; }", "E0003"),
+            ("fn f() { x := -@; y := z; }", "E0003"),
+        ] {
+            let diagnostics = compile(source).unwrap_err();
+            let codes: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+            assert_eq!(codes, vec![code, "E0203"], "{source}");
+        }
+    }
+
+    #[test]
+    fn test_warnings_dont_fail() {
+        let (program, diagnostics) = compile_with_diagnostics("fn f() { 007; }");
+        assert!(program.is_some());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "W0001");
+        assert!(!diagnostics[0].is_error());
+        assert!(compile("fn f() { 007; }").is_ok());
+    }
+
+    #[test]
+    fn test_assignment_in_condition_suggests_equal() {
+        let diagnostics = compile("fn f() { if a = b {} }").unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "E0105");
+        assert!(diagnostics[0].message.contains("`==`"));
+    }
+
+    #[test]
+    fn test_error_at_end_of_file_has_location() {
+        let diagnostics = compile("fn f() {\n  x;\n").unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].location,
+            Some(Location { line: 3, column: 1 })
+        );
+        assert!(diagnostics[0].message.ends_with("found end of file"));
+    }
+
+    #[test]
+    fn test_reports_every_parse_error() {
+        let diagnostics =
+            compile("fn f() { x = ; }\nfn g() { else }\nstruct S { a: i64 }").unwrap_err();
+        let codes: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(codes, vec!["E0101", "E0106"]);
+    }
+
+    #[test]
+    fn test_resolves_function_bodies() {
+        let diagnostics = compile("fn f() { let x: Foo; y := undefined_name; }").unwrap_err();
+        let codes: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(codes, vec!["E0201", "E0203"]);
+        assert!(diagnostics[1].message.contains("undefined_name"));
+    }
+
+    #[test]
+    fn test_messages_spell_tokens() {
+        let messages = |source| -> Vec<String> {
+            compile(source)
+                .unwrap_err()
+                .into_iter()
+                .map(|diagnostic| diagnostic.message)
+                .collect()
+        };
+        assert_eq!(messages("fn f() { x = !; }"), ["`!` needs an operand"]);
+        assert_eq!(
+            messages("fn f() { (a]; }"),
+            ["`(` opened at 1:10 is closed by `]`"]
+        );
+        assert_eq!(
+            messages("struct 1"),
+            ["expected one of `<identifier>`, found `<integer>`"]
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use crate::ast::{ParseError, ParseErrorKind};
+use crate::resolve::{ResolveError, ResolveErrorKind};
+use crate::token::{Location, Token, TokenKind};
+use crate::tokenizer::{TokenizingError, TokenizingErrorKind, TokenizingWarning};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in any stage of compilation.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The stable code of the originating error kind, like `E0001`.
+    pub code: &'static str,
+    /// `None` when the problem has no location in the source.
+    pub location: Option<Location>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub const fn is_error(&self) -> bool {
+        matches!(self.severity, Severity::Error)
+    }
+
+    /// Formats the diagnostic on a single line in the format that editors and
+    /// the GNU tools use, like `main.lang:3:7: error: invalid token [E0003]`.
+    pub fn terse(&self, path: &str) -> String {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match self.location {
+            Some(location) => format!(
+                "{}:{}:{}: {}: {} [{}]",
+                path, location.line, location.column, severity, self.message, self.code
+            ),
+            None => format!("{}: {}: {} [{}]", path, severity, self.message, self.code),
+        }
+    }
+}
+
+impl From<TokenizingError> for Diagnostic {
+    fn from(error: TokenizingError) -> Self {
+        let message = match error.kind {
+            TokenizingErrorKind::InvalidEscape => "invalid escape character",
+            TokenizingErrorKind::InvalidSuffix => "invalid suffix",
+            TokenizingErrorKind::InvalidCharLiteral => "invalid character literal",
+            TokenizingErrorKind::InvalidSeparator => "misplaced digit separator",
+            TokenizingErrorKind::InvalidDigit => "invalid digit for the base of the literal",
+            TokenizingErrorKind::MissingExponent => "missing exponent digits",
+            TokenizingErrorKind::UnknownToken => "invalid token",
+            TokenizingErrorKind::UnterminatedString => "unterminated string",
+            TokenizingErrorKind::UnterminatedComment => "unterminated block comment",
+            TokenizingErrorKind::IntegerOutOfRange => "integer literal out of range for its type",
+        };
+
+        Self {
+            severity: Severity::Error,
+            code: error.kind.code(),
+            location: Some(error.location),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<TokenizingWarning> for Diagnostic {
+    fn from(warning: TokenizingWarning) -> Self {
+        let message = match warning {
+            TokenizingWarning::RedundantLeadingZero { .. } => {
+                "redundant leading zero, integer literals are always decimal without a prefix"
+            }
+        };
+
+        Self {
+            severity: Severity::Warning,
+            code: warning.code(),
+            location: Some(warning.location()),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(error: ParseError) -> Self {
+        let message = match error.kind() {
+            ParseErrorKind::Expected(expected) => {
+                let found = match error.token().map(Token::kind) {
+                    Some(TokenKind::EndOfFile) | None => "end of file".to_string(),
+                    Some(kind) => format!("`{}`", kind),
+                };
+                let expected: Vec<_> = expected.iter().map(|kind| format!("`{}`", kind)).collect();
+                format!("expected one of {}, found {}", expected.join(", "), found)
+            }
+            ParseErrorKind::InvalidAssignmentTarget => {
+                let operator = error
+                    .token()
+                    .and_then(|token| token.kind().spelling())
+                    .unwrap_or("=");
+                format!("the left side of `{}` can't be assigned to", operator)
+            }
+            ParseErrorKind::AssignmentInCondition => {
+                "`=` assigns instead of comparing, use `==` to compare".to_string()
+            }
+            ParseErrorKind::ElseWithoutIf => "`else` without an `if` before it".to_string(),
+            ParseErrorKind::MissingOperand => match error.token() {
+                Some(token) => format!("`{}` needs an operand", token.kind()),
+                None => "operator needs an operand".to_string(),
+            },
+            ParseErrorKind::MismatchedBracket {
+                opened,
+                open_location,
+                found,
+            } => format!(
+                "`{}` opened at {}:{} is closed by `{}`",
+                opened, open_location.line, open_location.column, found
+            ),
+        };
+
+        Self {
+            severity: Severity::Error,
+            code: error.code(),
+            location: error.location(),
+            message,
+        }
+    }
+}
+
+impl From<ResolveError> for Diagnostic {
+    fn from(error: ResolveError) -> Self {
+        let code = error.kind.code();
+        let message = match error.kind {
+            ResolveErrorKind::UnknownType(name) => format!("unknown type `{}`", name),
+            ResolveErrorKind::DuplicateDefinition(name) => {
+                format!("`{}` is defined multiple times", name)
+            }
+            ResolveErrorKind::UnknownName(name) => format!("cannot find `{}` in scope", name),
+            ResolveErrorKind::UnassignedVariable(name) => {
+                format!("`{}` is used before anything is assigned to it", name)
+            }
+        };
+
+        Self {
+            severity: Severity::Error,
+            code,
+            location: Some(error.location),
+            message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_error_codes() {
+        let codes = [
+            (TokenizingErrorKind::InvalidEscape.code(), "E0001"),
+            (TokenizingErrorKind::InvalidSuffix.code(), "E0002"),
+            (TokenizingErrorKind::UnknownToken.code(), "E0003"),
+            (TokenizingErrorKind::InvalidSeparator.code(), "E0004"),
+            (TokenizingErrorKind::MissingExponent.code(), "E0005"),
+            (TokenizingErrorKind::InvalidCharLiteral.code(), "E0006"),
+            (TokenizingErrorKind::InvalidDigit.code(), "E0007"),
+            (TokenizingErrorKind::UnterminatedString.code(), "E0008"),
+            (TokenizingErrorKind::UnterminatedComment.code(), "E0009"),
+            (TokenizingErrorKind::IntegerOutOfRange.code(), "E0010"),
+            (ParseErrorKind::Expected(Vec::new()).code(), "E0101"),
+            (
+                ParseErrorKind::MismatchedBracket {
+                    opened: TokenKind::OpenParentheses,
+                    open_location: Location { line: 1, column: 1 },
+                    found: TokenKind::CloseBraces,
+                }
+                .code(),
+                "E0102",
+            ),
+            (ParseErrorKind::InvalidAssignmentTarget.code(), "E0103"),
+            (ParseErrorKind::MissingOperand.code(), "E0104"),
+            (ParseErrorKind::AssignmentInCondition.code(), "E0105"),
+            (ParseErrorKind::ElseWithoutIf.code(), "E0106"),
+            (ResolveErrorKind::UnknownType(String::new()).code(), "E0201"),
+            (
+                ResolveErrorKind::DuplicateDefinition(String::new()).code(),
+                "E0202",
+            ),
+            (ResolveErrorKind::UnknownName(String::new()).code(), "E0203"),
+            (
+                ResolveErrorKind::UnassignedVariable(String::new()).code(),
+                "E0204",
+            ),
+        ];
+
+        for (code, expected) in codes {
+            assert_eq!(code, expected);
+        }
+
+        let distinct: HashSet<_> = codes.iter().map(|(code, _)| code).collect();
+        assert_eq!(distinct.len(), codes.len());
+    }
+
+    #[test]
+    fn test_terse() {
+        let error = TokenizingError {
+            location: Location { line: 3, column: 7 },
+            kind: TokenizingErrorKind::InvalidEscape,
+        };
+        assert_eq!(
+            Diagnostic::from(error).terse("main.lang"),
+            "main.lang:3:7: error: invalid escape character [E0001]"
+        );
+
+        let warning = TokenizingWarning::RedundantLeadingZero {
+            location: Location { line: 1, column: 2 },
+        };
+        assert!(Diagnostic::from(warning)
+            .terse("a")
+            .starts_with("a:1:2: warning: "));
+
+        let at_end = Diagnostic {
+            severity: Severity::Error,
+            code: "E0101",
+            location: None,
+            message: "expected `}`".to_string(),
+        };
+        assert_eq!(at_end.terse("a"), "a: error: expected `}` [E0101]");
+    }
+}
@@ -0,0 +1,20 @@
+#![feature(never_type)]
+#![cfg_attr(test, feature(test))]
+
+pub mod ast;
+pub mod compile;
+pub mod diagnostic;
+pub mod resolve;
+pub mod token;
+pub mod tokenizer;
+
+pub use ast::{AbstractSyntaxTree, ParseError, ParseErrorKind, Program};
+pub use compile::{compile, compile_with_diagnostics};
+pub use diagnostic::{Diagnostic, Severity};
+pub use token::{Location, Span, Token, TokenKind};
+#[cfg(feature = "serde")]
+pub use tokenizer::tokens_to_json;
+pub use tokenizer::{
+    tokenize_text, tokenize_text_recovering, tokenize_text_with_options, IntegerParseError,
+    LiteralData, Tokenizer, TokenizerOptions, TokenizingError, TokenizingErrorKind,
+};
@@ -1,50 +1,171 @@
-#![feature(once_cell)]
-#![feature(never_type)]
-
-mod ast;
-mod token;
-mod tokenizer;
-
-use ast::Program;
-use tokenizer::{TokenizingError, TokenizingErrorKind};
+use lang_test::tokenizer;
+use lang_test::{
+    Diagnostic, Location, ParseError, Program, Span, Token, TokenizingError, TokenizingErrorKind,
+};
 
 fn main() {
     let contents = include_str!("../example.txt");
     match tokenizer::tokenize_text(contents) {
         Ok((tokens, literal_data)) => {
-            println!("{:#?}", tokens);
-            let program = Program::from_tokens(tokens, literal_data);
-            println!("{:#?}", program);
+            for token in &tokens {
+                println!("{}", tokenizer::describe_token(token, &literal_data));
+            }
+            match Program::from_tokens(tokens, literal_data) {
+                Ok(program) => println!("{:#?}", program),
+                Err(errors) => {
+                    for e in &errors {
+                        print!("{}", render_parse_error(contents, e));
+                    }
+                }
+            }
+        }
+        Err(e) => print!("{}", render_tokenizing_error(contents, &e)),
+    };
+}
+
+fn render_tokenizing_error(contents: &str, error: &TokenizingError) -> String {
+    let diagnostic = Diagnostic::from(error.clone());
+
+    let note = match error.kind {
+        TokenizingErrorKind::UnterminatedString | TokenizingErrorKind::UnterminatedComment => {
+            " unterminated, opened here"
         }
-        Err(e) => print_tokenizing_error(contents, &e),
+        _ => "",
     };
+
+    let location = error.location;
+    format!(
+        "ERROR: {}\n{}",
+        diagnostic.message,
+        source_snippet(
+            contents,
+            Span {
+                start: location,
+                end: location
+            },
+            note
+        )
+    )
+}
+
+fn render_parse_error(contents: &str, error: &ParseError) -> String {
+    let diagnostic = Diagnostic::from(error.clone());
+    let mut rendered = format!("ERROR: {}\n", diagnostic.message);
+    if let Some(span) = error.token().map(Token::span) {
+        rendered += &source_snippet(contents, span, "");
+    }
+    rendered
 }
 
-fn print_tokenizing_error(contents: &str, error: &TokenizingError) {
-    let line = contents
+fn source_line(contents: &str, location: Location) -> &str {
+    // the end of a file ending in a newline is on a line of its own
+    contents
         .lines()
-        .nth(error.location.line - 1)
-        .expect("ICE: error on non-existing line");
-
-    let message = match error.kind {
-        TokenizingErrorKind::InvalidEscape => format!(
-            "invalid escape character {} at column {} on line {}",
-            line.chars()
-                .nth(error.location.column - 1)
-                .expect("ICE: error at non-existing column"),
-            error.location.line,
-            error.location.column
-        ),
-        TokenizingErrorKind::InvalidSuffix => format!(
-            "invalid suffix starting from column {} on line {}",
-            error.location.column, error.location.line
-        ),
-        TokenizingErrorKind::UnknownToken => format!(
-            "invalid token starting from column {} on line {}",
-            error.location.column, error.location.line
-        ),
+        .nth(location.line - 1)
+        .or((location.line == contents.lines().count() + 1).then_some(""))
+        .expect("ICE: error on non-existing line")
+}
+
+/// The line where `span` starts with carets under the part of it that `span`
+/// covers, followed by `note`. There is always at least one caret, and a span
+/// continuing on later lines is underlined to the end of its first line.
+fn source_snippet(contents: &str, span: Span, note: &str) -> String {
+    let line = source_line(contents, span.start);
+    let prefix = format!("{}: ", span.start.line);
+    let start = span.start.column - 1;
+    let end = if span.end.line == span.start.line {
+        span.end.column - 1
+    } else {
+        line.chars().count()
     };
+    // keep tabs so that the carets line up with the source line
+    let padding: String = line
+        .chars()
+        .take(start)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let carets = "^".repeat(end.saturating_sub(start).max(1));
+    format!(
+        "{}{}\n{}{}{}{}\n",
+        prefix,
+        line.trim_end(),
+        " ".repeat(prefix.len()),
+        padding,
+        carets,
+        note
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use lang_test::tokenizer::tokenize_text;
+
+    use super::*;
+
+    fn render(contents: &str) -> String {
+        let error = tokenize_text(contents).unwrap_err();
+        assert_eq!(error.kind, TokenizingErrorKind::InvalidEscape);
+        render_tokenizing_error(contents, &error)
+    }
+
+    #[test]
+    fn test_render_invalid_escape() {
+        assert_eq!(
+            render("x := 1;\n\ts := \"a\\qb\";\n"),
+            "ERROR: invalid escape character\n\
+             2: \ts := \"a\\qb\";\n\
+             \x20  \t        ^\n"
+        );
+    }
+
+    #[test]
+    fn test_render_invalid_escape_at_line_end() {
+        // the escape is missing, so the error is just past the end of the line
+        assert_eq!(
+            render("\"a\\\nb\""),
+            "ERROR: invalid escape character\n1: \"a\\\n      ^\n"
+        );
+        assert_eq!(
+            render("\"\\u{41\n}\""),
+            "ERROR: invalid escape character\n1: \"\\u{41\n         ^\n"
+        );
+        assert_eq!(
+            render("'\\"),
+            "ERROR: invalid escape character\n1: '\\\n     ^\n"
+        );
+    }
+
+    #[test]
+    fn test_render_parse_error_underlines_token() {
+        let contents = "fn main() {\n    x := 1 +;\n}";
+        let (tokens, literal_data) = tokenize_text(contents).unwrap();
+        let error = Program::from_tokens(tokens, literal_data)
+            .unwrap_err()
+            .remove(0);
+        let rendered = render_parse_error(contents, &error);
+        let lines: Vec<_> = rendered.lines().collect();
+        assert_eq!(lines[1], "2:     x := 1 +;");
+        // under the `;` where the operand is missing
+        assert_eq!(lines[2], "               ^");
+
+        let contents = "fn main() -> 123 {}";
+        let (tokens, literal_data) = tokenize_text(contents).unwrap();
+        let error = Program::from_tokens(tokens, literal_data)
+            .unwrap_err()
+            .remove(0);
+        let rendered = render_parse_error(contents, &error);
+        assert_eq!(rendered.lines().nth(2), Some("                ^^^"));
+    }
 
-    println!("ERROR: {}", message);
-    println!("{}: {}", error.location.line, line.trim());
+    #[test]
+    fn test_multi_line_span() {
+        let span = Span {
+            start: Location { line: 1, column: 3 },
+            end: Location { line: 2, column: 2 },
+        };
+        assert_eq!(
+            source_snippet("a \"bc\n\"", span, ""),
+            "1: a \"bc\n     ^^^\n"
+        );
+    }
 }
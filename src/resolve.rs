@@ -0,0 +1,406 @@
+use std::collections::HashMap;
+
+use crate::ast::{CodeBlock, ElseBranch, Expression, Function, Program, Statement, Type};
+use crate::token::Location;
+
+const PRIMITIVE_TYPES: [&str; 15] = [
+    "bool", "char", "str", "i8", "i16", "i32", "i64", "isize", "u8", "u16", "u32", "u64", "usize",
+    "f32", "f64",
+];
+
+#[derive(Debug)]
+pub struct ResolveError {
+    pub location: Location,
+    pub kind: ResolveErrorKind,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ResolveErrorKind {
+    /// A type name that is neither a primitive nor a struct defined in the
+    /// program. The location is that of the definition using the type.
+    UnknownType(String),
+    DuplicateDefinition(String),
+    /// A name in a function body that is neither a variable in scope nor a
+    /// function. The location is that of the function.
+    UnknownName(String),
+    /// A variable declared by a `let` without a value that is used before
+    /// anything is assigned to it. The location is that of the function.
+    UnassignedVariable(String),
+}
+
+impl ResolveErrorKind {
+    /// A short code identifying the kind of error, see `DESIGN.md`. Codes must
+    /// never change or be reused, new kinds get the next free code.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::UnknownType(_) => "E0201",
+            Self::DuplicateDefinition(_) => "E0202",
+            Self::UnknownName(_) => "E0203",
+            Self::UnassignedVariable(_) => "E0204",
+        }
+    }
+}
+
+/// Checks that every name used in the program refers to something defined,
+/// returning all problems found.
+pub fn resolve_names(program: &Program) -> Vec<ResolveError> {
+    let mut errors = Vec::new();
+
+    let mut structs = HashMap::new();
+    for structure in &program.structs {
+        if structs.insert(structure.name.as_str(), structure).is_some() {
+            errors.push(ResolveError {
+                location: structure.location,
+                kind: ResolveErrorKind::DuplicateDefinition(structure.name.clone()),
+            });
+        }
+    }
+
+    let mut functions = HashMap::new();
+    for function in &program.functions {
+        if functions.insert(function.name.as_str(), function).is_some() {
+            errors.push(ResolveError {
+                location: function.location,
+                kind: ResolveErrorKind::DuplicateDefinition(function.name.clone()),
+            });
+        }
+    }
+
+    let is_known_type = |name: &str| PRIMITIVE_TYPES.contains(&name) || structs.contains_key(name);
+
+    for structure in &program.structs {
+        let is_known_type = |name: &str| {
+            structure.type_params.iter().any(|param| param == name) || is_known_type(name)
+        };
+        for (_, field_type) in &structure.fields {
+            resolve_type(field_type, structure.location, &is_known_type, &mut errors);
+        }
+    }
+
+    for function in &program.functions {
+        let is_known_type = |name: &str| {
+            function.type_params.iter().any(|param| param == name) || is_known_type(name)
+        };
+        for argument in &function.arguments {
+            resolve_type(
+                &argument.argument_type,
+                function.location,
+                &is_known_type,
+                &mut errors,
+            );
+        }
+        resolve_type(
+            &function.return_type,
+            function.location,
+            &is_known_type,
+            &mut errors,
+        );
+
+        let mut body = BodyResolver {
+            location: function.location,
+            is_known_type: &is_known_type,
+            functions: &functions,
+            scopes: vec![function
+                .arguments
+                .iter()
+                .map(|argument| (argument.name.as_str(), true))
+                .collect()],
+            errors: &mut errors,
+        };
+        body.resolve_block(&function.body);
+    }
+
+    errors
+}
+
+/// Resolves the variable names of a function body. Each scope maps the
+/// variables declared in it to whether they have been assigned to. This isn't
+/// flow-sensitive: an assignment anywhere before a use counts, even in a
+/// branch that may not run.
+struct BodyResolver<'a, 'p> {
+    location: Location,
+    is_known_type: &'a dyn Fn(&str) -> bool,
+    functions: &'a HashMap<&'p str, &'p Function>,
+    scopes: Vec<HashMap<&'p str, bool>>,
+    errors: &'a mut Vec<ResolveError>,
+}
+
+impl<'p> BodyResolver<'_, 'p> {
+    fn resolve_block(&mut self, block: &'p CodeBlock) {
+        self.scopes.push(HashMap::new());
+        for statement in &block.statements {
+            self.resolve_statement(statement);
+        }
+        if let Some(value) = &block.value {
+            self.resolve_expression(value);
+        }
+        self.scopes.pop();
+    }
+
+    fn error(&mut self, kind: ResolveErrorKind) {
+        self.errors.push(ResolveError {
+            location: self.location,
+            kind,
+        });
+    }
+
+    fn variable(&mut self, name: &str) -> Option<&mut bool> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+
+    fn resolve_statement(&mut self, statement: &'p Statement) {
+        match statement {
+            Statement::Expression(expression) | Statement::Return(Some(expression)) => {
+                self.resolve_expression(expression);
+            }
+            Statement::While {
+                condition, body, ..
+            } => {
+                self.resolve_expression(condition);
+                self.resolve_block(body);
+            }
+            Statement::Break { .. } | Statement::Continue { .. } | Statement::Return(None) => (),
+            Statement::Assign {
+                target: Expression::Identifier(name),
+                value,
+            } => {
+                self.resolve_expression(value);
+                match self.variable(name) {
+                    Some(assigned) => *assigned = true,
+                    None => self.error(ResolveErrorKind::UnknownName(name.clone())),
+                }
+            }
+            Statement::Assign { target, value }
+            | Statement::CompoundAssign { target, value, .. } => {
+                self.resolve_expression(value);
+                self.resolve_expression(target);
+            }
+            Statement::Let {
+                name, ty, value, ..
+            } => {
+                if let Some(ty) = ty {
+                    resolve_type(ty, self.location, &self.is_known_type, self.errors);
+                }
+                // the value is resolved before the name is in scope, so
+                // `x := x + 1` uses an earlier `x`
+                if let Some(value) = value {
+                    self.resolve_expression(value);
+                }
+                self.scopes
+                    .last_mut()
+                    .expect("ICE: statement outside of a scope")
+                    .insert(name, value.is_some());
+            }
+        }
+    }
+
+    fn resolve_expression(&mut self, expression: &'p Expression) {
+        match expression {
+            Expression::Identifier(name) => match self.variable(name).copied() {
+                Some(true) => (),
+                Some(false) => self.error(ResolveErrorKind::UnassignedVariable(name.clone())),
+                None if self.functions.contains_key(name.as_str()) => (),
+                None => self.error(ResolveErrorKind::UnknownName(name.clone())),
+            },
+            Expression::IntegerLiteral { .. }
+            | Expression::FloatLiteral(_)
+            | Expression::StringLiteral(_)
+            | Expression::CharLiteral(_)
+            | Expression::BoolLiteral(_)
+            | Expression::Return(None)
+            | Expression::Error => (),
+            Expression::Tuple(elements) => {
+                for element in elements {
+                    self.resolve_expression(element);
+                }
+            }
+            Expression::Unary { operand, .. } => self.resolve_expression(operand),
+            Expression::Binary { left, right, .. } => {
+                self.resolve_expression(left);
+                self.resolve_expression(right);
+            }
+            Expression::Field { base, .. } => self.resolve_expression(base),
+            Expression::Index { base, index } => {
+                self.resolve_expression(base);
+                self.resolve_expression(index);
+            }
+            Expression::Call { callee, arguments } => {
+                self.resolve_expression(callee);
+                for argument in arguments {
+                    self.resolve_expression(argument);
+                }
+            }
+            Expression::If {
+                condition,
+                body,
+                else_branch,
+            } => {
+                self.resolve_expression(condition);
+                self.resolve_block(body);
+                match else_branch {
+                    Some(ElseBranch::Block(block)) => self.resolve_block(block),
+                    Some(ElseBranch::If(if_expression)) => self.resolve_expression(if_expression),
+                    None => (),
+                }
+            }
+            Expression::Block(block) => self.resolve_block(block),
+            Expression::Return(Some(value)) => self.resolve_expression(value),
+            Expression::StructLiteral { fields, .. } => {
+                for (_, value) in fields {
+                    self.resolve_expression(value);
+                }
+            }
+            Expression::Match { scrutinee, arms } => {
+                self.resolve_expression(scrutinee);
+                for (_, value) in arms {
+                    self.resolve_expression(value);
+                }
+            }
+        }
+    }
+}
+
+fn resolve_type(
+    ty: &Type,
+    location: Location,
+    is_known_type: &impl Fn(&str) -> bool,
+    errors: &mut Vec<ResolveError>,
+) {
+    match ty {
+        Type::Named { name, args } => {
+            if !is_known_type(name) {
+                errors.push(ResolveError {
+                    location,
+                    kind: ResolveErrorKind::UnknownType(name.clone()),
+                });
+            }
+            for arg in args {
+                resolve_type(arg, location, is_known_type, errors);
+            }
+        }
+        Type::Reference(inner) | Type::Array(inner) => {
+            resolve_type(inner, location, is_known_type, errors);
+        }
+        Type::Unit => (),
+        Type::Tuple(types) => {
+            for ty in types {
+                resolve_type(ty, location, is_known_type, errors);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::tokenize_text;
+
+    fn resolve(input: &str) -> Vec<ResolveError> {
+        let (tokens, literal_data) = tokenize_text(input).unwrap();
+        resolve_names(&Program::from_tokens(tokens, literal_data).unwrap())
+    }
+
+    #[test]
+    fn test_known_types() {
+        assert!(resolve("struct A { x: i64 } struct B { a: &A, b: [(bool, u8)] }").is_empty());
+        assert!(resolve("struct C { len: usize, offset: isize }").is_empty());
+        assert!(resolve("fn f(i: usize) -> isize { let x: usize = i; }").is_empty());
+    }
+
+    #[test]
+    fn test_literal_types() {
+        for input in [
+            "fn f() { let b: bool = true; }",
+            "fn f() { let c: char = 'a'; }",
+            "fn f() { let s: &str = \"a\"; }",
+            "fn f() { let i: i64 = 1; }",
+            "fn f() { let x: f32 = 1.0; }",
+            "fn f() { let d: f64 = 1.0; }",
+        ] {
+            assert!(resolve(input).is_empty(), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_unknown_type() {
+        let errors = resolve("struct A { x: i64, y: Foo }");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].kind,
+            ResolveErrorKind::UnknownType("Foo".to_string())
+        );
+        assert_eq!(errors[0].location, Location { line: 1, column: 8 });
+    }
+
+    #[test]
+    fn test_duplicate_struct() {
+        let errors = resolve("struct A { }\nstruct A { }");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].kind,
+            ResolveErrorKind::DuplicateDefinition("A".to_string())
+        );
+        assert_eq!(errors[0].location, Location { line: 2, column: 8 });
+    }
+
+    #[test]
+    fn test_type_params() {
+        assert!(resolve("struct Box<T> { value: T }\nfn id<U>(x: U) -> Box(U) {}").is_empty());
+        assert_eq!(
+            resolve("struct A<T> { a: T }\nstruct B { b: T }")
+                .into_iter()
+                .map(|error| error.kind)
+                .collect::<Vec<_>>(),
+            vec![ResolveErrorKind::UnknownType("T".to_string())]
+        );
+    }
+
+    fn error_kinds(input: &str) -> Vec<ResolveErrorKind> {
+        resolve(input).into_iter().map(|error| error.kind).collect()
+    }
+
+    #[test]
+    fn test_variables() {
+        assert!(resolve(
+            "fn f(a: i64) -> i64 { b := a; let c: i64; c = b; { d := c; } g(a, c) }\n\
+             fn g(x: i64, y: i64) -> i64 { x := x + y; x }"
+        )
+        .is_empty());
+
+        let errors = resolve("fn f() { let x: i64; y := undefined_name; }");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].kind,
+            ResolveErrorKind::UnknownName("undefined_name".to_string())
+        );
+        assert_eq!(errors[0].location, Location { line: 1, column: 4 });
+
+        // a block's variables end with it
+        assert_eq!(
+            error_kinds("fn f() { { a := 1; } a; b = 2; }"),
+            vec![
+                ResolveErrorKind::UnknownName("a".to_string()),
+                ResolveErrorKind::UnknownName("b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unassigned_variables() {
+        assert_eq!(
+            error_kinds("fn f() { let x: i64; y := x + 1; x = 2; z := x; x += 1; }"),
+            vec![ResolveErrorKind::UnassignedVariable("x".to_string())]
+        );
+        // compound assignments read the variable
+        assert_eq!(
+            error_kinds("fn f() { let mut x; x *= 2; }"),
+            vec![ResolveErrorKind::UnassignedVariable("x".to_string())]
+        );
+        assert!(
+            resolve("fn f() { let x; if c() { x = 1; } x; }\nfn c() -> bool { true }").is_empty()
+        );
+    }
+}
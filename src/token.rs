@@ -1,103 +1,409 @@
-#[derive(Debug)]
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasherDefault, Hasher};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     token_kind: TokenKind,
-    location: Location,
+    span: Span,
 }
 
 impl Token {
-    pub const fn new(token_kind: TokenKind, location: Location) -> Self {
-        Self {
-            token_kind,
-            location,
-        }
+    pub const fn new(token_kind: TokenKind, span: Span) -> Self {
+        Self { token_kind, span }
     }
 
     pub const fn kind(&self) -> TokenKind {
         self.token_kind
     }
 
+    /// The location of the first char of the token.
     pub const fn location(&self) -> Location {
-        self.location
+        self.span.start
+    }
+
+    pub const fn span(&self) -> Span {
+        self.span
     }
 }
 
+/// The part of the source from `start` up to but not including `end`. The
+/// `EndOfFile` token is empty, with `start` and `end` the same.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: Location,
+    pub end: Location,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Location {
     pub line: usize,
     pub column: usize,
 }
 
+/// A map keyed by locations generated by the tokenizer. These aren't
+/// attacker controlled, so a fast hash is fine.
+pub type LocationMap<V> = HashMap<Location, V, BuildHasherDefault<LocationHasher>>;
+
+/// The multiply-rotate hash used by rustc, which is fast for the two
+/// integers of a `Location`.
+#[derive(Default)]
+pub struct LocationHasher {
+    hash: u64,
+}
+
+impl LocationHasher {
+    const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+    fn add(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(Self::SEED);
+    }
+}
+
+impl Hasher for LocationHasher {
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.add(u64::from(byte));
+        }
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.add(n as u64);
+    }
+}
+
 #[allow(clippy::module_name_repetitions)]
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TokenKind {
     StringLiteral,
     IntegerLiteral,
+    FloatLiteral,
+    CharLiteral,
+    BoolLiteral,
     Identifier,
+    Label,
     OpenBraces,
     CloseBraces,
     OpenParentheses,
     CloseParentheses,
+    OpenBrackets,
+    CloseBrackets,
     Plus,
     Minus,
     Mul,
     Div,
     Rem,
     Assign,
+    PlusAssign,
+    MinusAssign,
+    MulAssign,
+    DivAssign,
+    RemAssign,
     DefineVar,
     FieldTypeSeparator,
     Equal,
+    NotEqual,
     Greater,
     GreaterOrEqual,
     Less,
     LessOrEqual,
+    ShiftLeft,
+    ShiftRight,
     FunctionDefinition,
     Mutable,
     Struct,
+    While,
+    Break,
+    Continue,
+    Return,
+    Let,
+    Use,
+    If,
+    Else,
+    Match,
     Not,
     Or,
     And,
     Xor,
+    LogicalAnd,
+    LogicalOr,
     Comma,
+    Dot,
     EndOfStatement,
     RightArrow,
+    FatArrow,
+    Underscore,
+    PathSep,
+    Hash,
+    /// Stands in for input that failed to tokenize, so that the parser can
+    /// carry on past it. Never produced by the tokenizer itself.
+    Error,
+    EndOfFile,
 }
 
-/*
-// TODO: is this even ever used?
+impl TokenKind {
+    /// Whether tokens of this kind carry no meaning for parsing.
+    pub const fn is_trivia(self) -> bool {
+        matches!(self, TokenKind::EndOfFile)
+    }
+
+    /// Whether the kind is a word that can't be an identifier, like `fn`.
+    pub fn is_keyword(self) -> bool {
+        self.spelling().and_then(keyword_for) == Some(self)
+    }
+
+    /// How a token of this kind is always written, or `None` for the kinds
+    /// with varying text, like identifiers and literals.
+    pub const fn spelling(self) -> Option<&'static str> {
+        let spelling = match self {
+            Self::OpenBraces => "{",
+            Self::CloseBraces => "}",
+            Self::OpenParentheses => "(",
+            Self::CloseParentheses => ")",
+            Self::OpenBrackets => "[",
+            Self::CloseBrackets => "]",
+            Self::Plus => "+",
+            Self::Minus => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Rem => "%",
+            Self::Assign => "=",
+            Self::PlusAssign => "+=",
+            Self::MinusAssign => "-=",
+            Self::MulAssign => "*=",
+            Self::DivAssign => "/=",
+            Self::RemAssign => "%=",
+            Self::Equal => "==",
+            Self::NotEqual => "!=",
+            Self::Greater => ">",
+            Self::GreaterOrEqual => ">=",
+            Self::Less => "<",
+            Self::LessOrEqual => "<=",
+            Self::ShiftLeft => "<<",
+            Self::ShiftRight => ">>",
+            Self::FunctionDefinition => "fn",
+            Self::Mutable => "mut",
+            Self::Struct => "struct",
+            Self::While => "while",
+            Self::Break => "break",
+            Self::Continue => "continue",
+            Self::Return => "return",
+            Self::Let => "let",
+            Self::Use => "use",
+            Self::If => "if",
+            Self::Else => "else",
+            Self::Match => "match",
+            Self::DefineVar => ":=",
+            Self::FieldTypeSeparator => ":",
+            Self::Not => "!",
+            Self::Or => "|",
+            Self::And => "&",
+            Self::Xor => "^",
+            Self::LogicalAnd => "&&",
+            Self::LogicalOr => "||",
+            Self::Comma => ",",
+            Self::Dot => ".",
+            Self::EndOfStatement => ";",
+            Self::Hash => "#",
+            Self::PathSep => "::",
+            Self::RightArrow => "->",
+            Self::FatArrow => "=>",
+            Self::Underscore => "_",
+            Self::StringLiteral
+            | Self::Identifier
+            | Self::IntegerLiteral
+            | Self::FloatLiteral
+            | Self::CharLiteral
+            | Self::BoolLiteral
+            | Self::Label
+            | Self::Error
+            | Self::EndOfFile => return None,
+        };
+        Some(spelling)
+    }
+}
+
+/// The kind of the reserved word `word`, or `None` if it is an identifier.
+/// `true` and `false` are reserved as `BoolLiteral`s.
+pub fn keyword_for(word: &str) -> Option<TokenKind> {
+    let kind = match word {
+        "fn" => TokenKind::FunctionDefinition,
+        "mut" => TokenKind::Mutable,
+        "struct" => TokenKind::Struct,
+        "while" => TokenKind::While,
+        "break" => TokenKind::Break,
+        "continue" => TokenKind::Continue,
+        "return" => TokenKind::Return,
+        "use" => TokenKind::Use,
+        "let" => TokenKind::Let,
+        "if" => TokenKind::If,
+        "else" => TokenKind::Else,
+        "match" => TokenKind::Match,
+        "true" | "false" => TokenKind::BoolLiteral,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// Writes the spelling of the kind, or a placeholder like `<identifier>` for
+/// kinds without a fixed spelling.
 impl fmt::Display for TokenKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let s = match self {
-            TokenKind::OpenBraces => "{",
-            TokenKind::CloseBraces => "}",
-            TokenKind::OpenParentheses => "(",
-            TokenKind::CloseParentheses => ")",
-            TokenKind::Plus => "+",
-            TokenKind::Minus => "-",
-            TokenKind::Mul => "*",
-            TokenKind::Div => "/",
-            TokenKind::Rem => "%",
-            TokenKind::Assign => "=",
-            TokenKind::Equal => "==",
-            TokenKind::Greater => ">",
-            TokenKind::GreaterOrEqual => ">=",
-            TokenKind::Less => "<",
-            TokenKind::LessOrEqual => "<=",
-            TokenKind::FunctionDefinition => "fn",
-            TokenKind::Mutable => "mut",
-            TokenKind::Struct => "struct",
-            TokenKind::DefineVar => ":=",
-            TokenKind::FieldTypeSeparator => ":",
-            TokenKind::Not => "!",
-            TokenKind::Or => "|",
-            TokenKind::And => "&",
-            TokenKind::Xor => "^",
-            TokenKind::Comma => ",",
-            TokenKind::EndOfStatement => ";",
-            TokenKind::StringLiteral => todo!(),
-            TokenKind::Identifier => todo!(),
-            TokenKind::IntegerLiteral => todo!(),
+        let placeholder = match self {
+            Self::StringLiteral => "<string>",
+            Self::Identifier => "<identifier>",
+            Self::IntegerLiteral => "<integer>",
+            Self::FloatLiteral => "<float>",
+            Self::CharLiteral => "<char>",
+            Self::BoolLiteral => "<bool>",
+            Self::Label => "<label>",
+            Self::Error => "<error>",
+            Self::EndOfFile => "<end of file>",
+            _ => "",
         };
-        write!(f, "{}", s)
+        write!(f, "{}", self.spelling().unwrap_or(placeholder))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::ast::Program;
+    use crate::tokenizer::{tokenize_text_with_options, HashRole, TokenizerOptions};
+
+    // keep in sync with `TokenKind`
+    const ALL_KINDS: [TokenKind; 62] = [
+        TokenKind::StringLiteral,
+        TokenKind::IntegerLiteral,
+        TokenKind::FloatLiteral,
+        TokenKind::CharLiteral,
+        TokenKind::BoolLiteral,
+        TokenKind::Identifier,
+        TokenKind::Label,
+        TokenKind::OpenBraces,
+        TokenKind::CloseBraces,
+        TokenKind::OpenParentheses,
+        TokenKind::CloseParentheses,
+        TokenKind::OpenBrackets,
+        TokenKind::CloseBrackets,
+        TokenKind::Plus,
+        TokenKind::Minus,
+        TokenKind::Mul,
+        TokenKind::Div,
+        TokenKind::Rem,
+        TokenKind::Assign,
+        TokenKind::PlusAssign,
+        TokenKind::MinusAssign,
+        TokenKind::MulAssign,
+        TokenKind::DivAssign,
+        TokenKind::RemAssign,
+        TokenKind::DefineVar,
+        TokenKind::FieldTypeSeparator,
+        TokenKind::Equal,
+        TokenKind::NotEqual,
+        TokenKind::Greater,
+        TokenKind::GreaterOrEqual,
+        TokenKind::Less,
+        TokenKind::LessOrEqual,
+        TokenKind::ShiftLeft,
+        TokenKind::ShiftRight,
+        TokenKind::FunctionDefinition,
+        TokenKind::Mutable,
+        TokenKind::Struct,
+        TokenKind::While,
+        TokenKind::Break,
+        TokenKind::Continue,
+        TokenKind::Return,
+        TokenKind::Let,
+        TokenKind::Use,
+        TokenKind::If,
+        TokenKind::Else,
+        TokenKind::Match,
+        TokenKind::Not,
+        TokenKind::Or,
+        TokenKind::And,
+        TokenKind::Xor,
+        TokenKind::LogicalAnd,
+        TokenKind::LogicalOr,
+        TokenKind::Comma,
+        TokenKind::Dot,
+        TokenKind::EndOfStatement,
+        TokenKind::RightArrow,
+        TokenKind::FatArrow,
+        TokenKind::Underscore,
+        TokenKind::PathSep,
+        TokenKind::Hash,
+        TokenKind::Error,
+        TokenKind::EndOfFile,
+    ];
+
+    #[test]
+    fn test_keywords() {
+        for kind in ALL_KINDS {
+            // `true` and `false` are reserved as literals without a spelling
+            if let Some(spelling) = kind.spelling() {
+                let is_word = spelling.chars().all(char::is_alphabetic);
+                assert_eq!(kind.is_keyword(), is_word, "{:?}", kind);
+            }
+        }
+        assert_eq!(keyword_for("true"), Some(TokenKind::BoolLiteral));
+        assert_eq!(keyword_for("fnord"), None);
+        assert_eq!(keyword_for("Fn"), None);
+        assert!(!TokenKind::Identifier.is_keyword());
+    }
+
+    #[test]
+    fn test_spellings_round_trip() {
+        let options = TokenizerOptions {
+            hash: HashRole::Token,
+            ..TokenizerOptions::default()
+        };
+
+        let mut spellings = HashSet::new();
+        for kind in ALL_KINDS {
+            let Some(spelling) = kind.spelling() else {
+                assert!(kind.to_string().starts_with('<'), "{:?}", kind);
+                continue;
+            };
+            assert_eq!(kind.to_string(), spelling);
+            assert!(spellings.insert(spelling), "{:?}", kind);
+
+            let (tokens, _) = tokenize_text_with_options(spelling, &options).unwrap();
+            let kinds: Vec<_> = tokens.iter().map(Token::kind).collect();
+            assert_eq!(kinds, &[kind, TokenKind::EndOfFile], "{:?}", spelling);
+        }
+    }
+
+    #[test]
+    fn test_clone_token_stream() {
+        let (tokens, literal_data) =
+            tokenize_text_with_options("fn f() -> Int { 1 }", &TokenizerOptions::default())
+                .unwrap();
+        let cloned = tokens.clone();
+        assert_eq!(cloned, tokens);
+
+        // the AST nodes can be cloned too
+        let program = Program::from_tokens(tokens, literal_data).unwrap();
+        let cloned = program.clone();
+        assert_eq!(cloned.functions[0].body, program.functions[0].body);
+        assert_eq!(cloned.to_string(), program.to_string());
+    }
+
+    #[test]
+    fn test_display_never_empty() {
+        assert_eq!(format!("{}", TokenKind::Identifier), "<identifier>");
+        for kind in ALL_KINDS {
+            assert!(!kind.to_string().is_empty(), "{:?}", kind);
+        }
     }
 }
-*/
@@ -1,42 +1,88 @@
 use std::collections::HashMap;
-use std::lazy::SyncLazy;
 use std::str::Chars;
+use std::sync::LazyLock;
 
 use unicode_xid::UnicodeXID;
 
-use crate::token::{Location, Token, TokenKind};
+use crate::token::{keyword_for, Location, LocationMap, Span, Token, TokenKind};
 
-const OTHER_TOKENS: [(&[char], TokenKind); 24] = [
+const OTHER_TOKENS: [(&[char], TokenKind); 40] = [
     (&['{'], TokenKind::OpenBraces),
     (&['}'], TokenKind::CloseBraces),
     (&['('], TokenKind::OpenParentheses),
     (&[')'], TokenKind::CloseParentheses),
+    (&['['], TokenKind::OpenBrackets),
+    (&[']'], TokenKind::CloseBrackets),
     (&['+'], TokenKind::Plus),
     (&['-'], TokenKind::Minus),
     (&['*'], TokenKind::Mul),
     (&['/'], TokenKind::Div),
     (&['%'], TokenKind::Rem),
     (&['='], TokenKind::Assign),
+    (&['+', '='], TokenKind::PlusAssign),
+    (&['-', '='], TokenKind::MinusAssign),
+    (&['*', '='], TokenKind::MulAssign),
+    (&['/', '='], TokenKind::DivAssign),
+    (&['%', '='], TokenKind::RemAssign),
     (&['=', '='], TokenKind::Equal),
     (&[':'], TokenKind::FieldTypeSeparator),
     (&[':', '='], TokenKind::DefineVar),
+    (&[':', ':'], TokenKind::PathSep),
     (&['>'], TokenKind::Greater),
     (&['>', '='], TokenKind::GreaterOrEqual),
     (&['<'], TokenKind::Less),
     (&['<', '='], TokenKind::LessOrEqual),
+    (&['<', '<'], TokenKind::ShiftLeft),
+    (&['>', '>'], TokenKind::ShiftRight),
     (&['!'], TokenKind::Not),
+    (&['!', '='], TokenKind::NotEqual),
     (&['|'], TokenKind::Or),
     (&['&'], TokenKind::And),
     (&['^'], TokenKind::Xor),
+    (&['&', '&'], TokenKind::LogicalAnd),
+    (&['|', '|'], TokenKind::LogicalOr),
     (&[','], TokenKind::Comma),
+    (&['.'], TokenKind::Dot),
     (&[';'], TokenKind::EndOfStatement),
     (&['-', '>'], TokenKind::RightArrow),
+    (&['=', '>'], TokenKind::FatArrow),
+    (&['#'], TokenKind::Hash),
 ];
 
-static TOKEN_MAP: SyncLazy<HashMap<&[char], Option<TokenKind>>> = SyncLazy::new(|| {
+/// The suffixes an integer literal can have and the largest value each
+/// allows. Signed types allow the magnitude of their minimum, so that `-128i8`
+/// is fine even though `128i8` on its own isn't really.
+const INTEGER_SUFFIXES: [(&str, u64); 10] = [
+    ("i8", 1 << 7),
+    ("i16", 1 << 15),
+    ("i32", 1 << 31),
+    ("i64", 1 << 63),
+    ("isize", 1 << 63),
+    ("u8", u8::MAX as u64),
+    ("u16", u16::MAX as u64),
+    ("u32", u32::MAX as u64),
+    ("u64", u64::MAX),
+    ("usize", u64::MAX),
+];
+
+/// The number of chars in the longest of `OTHER_TOKENS`.
+const LONGEST_OTHER_TOKEN: usize = {
+    let mut longest = 0;
+    let mut i = 0;
+    while i < OTHER_TOKENS.len() {
+        if OTHER_TOKENS[i].0.len() > longest {
+            longest = OTHER_TOKENS[i].0.len();
+        }
+        i += 1;
+    }
+    longest
+};
+
+static TOKEN_MAP: LazyLock<HashMap<&[char], Option<TokenKind>>> = LazyLock::new(|| {
     let mut token_map = HashMap::new();
     for (token_chars, token) in OTHER_TOKENS {
-        for l in 0..token_chars.len() - 1 {
+        // every proper prefix, even the ones that aren't tokens themselves
+        for l in 0..token_chars.len() {
             token_map.entry(&token_chars[..l]).or_insert(None);
         }
         token_map.insert(token_chars, Some(token));
@@ -44,10 +90,59 @@ static TOKEN_MAP: SyncLazy<HashMap<&[char], Option<TokenKind>>> = SyncLazy::new(
     token_map
 });
 
+#[derive(Clone, Debug, Default)]
+pub struct TokenizerOptions {
+    /// Record the source text of each token along with the whitespace and
+    /// comments before it, so that the input can be reconstructed exactly.
+    pub keep_trivia: bool,
+    /// Warn about decimal literals with a redundant leading zero, like `007`.
+    pub warn_leading_zeros: bool,
+    pub hash: HashRole,
+    /// Skip a first line starting with `#!`, whatever the role of `#` is.
+    pub skip_shebang: bool,
+}
+
+/// What a `#` outside of strings and comments means.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum HashRole {
+    /// Starts a comment that runs to the end of the line.
+    #[default]
+    Comment,
+    /// Is a `TokenKind::Hash` token.
+    Token,
+}
+
+/// Everything produced by `tokenize_text_recovering`.
+#[derive(Debug)]
+pub struct TokenizerOutput {
+    /// Always ends with an `EndOfFile` token.
+    pub tokens: Vec<Token>,
+    pub literal_data: LiteralData,
+    pub errors: Vec<TokenizingError>,
+    pub warnings: Vec<TokenizingWarning>,
+}
+
+/// The exact source text of a token, recorded when tokenizing with
+/// `keep_trivia`.
+#[derive(Debug)]
+pub struct TokenSource {
+    /// Whitespace, comments and anything that failed to tokenize between the
+    /// previous token and this one.
+    pub leading_trivia: String,
+    pub spelling: String,
+}
+
+#[derive(Debug, Default)]
 pub struct LiteralData {
-    identifiers: HashMap<Location, String>,
-    integer_literals: HashMap<Location, String>,
-    string_literals: HashMap<Location, String>,
+    identifiers: LocationMap<String>,
+    integer_literals: LocationMap<String>,
+    float_literals: LocationMap<String>,
+    integer_suffixes: LocationMap<String>,
+    string_literals: LocationMap<String>,
+    char_literals: LocationMap<char>,
+    bool_literals: LocationMap<bool>,
+    labels: LocationMap<String>,
+    token_sources: LocationMap<TokenSource>,
 }
 
 impl LiteralData {
@@ -63,15 +158,58 @@ impl LiteralData {
             .flatten()
     }
 
+    /// The value of an integer literal, or why it isn't a `u64`.
+    pub fn try_get_integer_value(&self, token: &Token) -> Option<Result<u64, IntegerParseError>> {
+        self.try_get_integer_literal(token)
+            .map(|text| integer_value(text))
+    }
+
+    /// The type suffix of an integer literal, like the `u8` of `255u8`.
+    pub fn try_get_integer_suffix(&self, token: &Token) -> Option<&String> {
+        (token.kind() == TokenKind::IntegerLiteral)
+            .then(|| self.integer_suffixes.get(&token.location()))
+            .flatten()
+    }
+
+    pub fn try_get_float_literal(&self, token: &Token) -> Option<&String> {
+        (token.kind() == TokenKind::FloatLiteral)
+            .then(|| self.float_literals.get(&token.location()))
+            .flatten()
+    }
+
     pub fn try_get_string_literal(&self, token: &Token) -> Option<&String> {
         (token.kind() == TokenKind::StringLiteral)
             .then(|| self.string_literals.get(&token.location()))
             .flatten()
     }
+
+    pub fn try_get_char_literal(&self, token: &Token) -> Option<char> {
+        (token.kind() == TokenKind::CharLiteral)
+            .then(|| self.char_literals.get(&token.location()).copied())
+            .flatten()
+    }
+
+    pub fn try_get_bool_literal(&self, token: &Token) -> Option<bool> {
+        (token.kind() == TokenKind::BoolLiteral)
+            .then(|| self.bool_literals.get(&token.location()).copied())
+            .flatten()
+    }
+
+    pub fn try_get_label(&self, token: &Token) -> Option<&String> {
+        (token.kind() == TokenKind::Label)
+            .then(|| self.labels.get(&token.location()))
+            .flatten()
+    }
+
+    /// Only available when tokenizing with `keep_trivia`.
+    pub fn try_get_token_source(&self, token: &Token) -> Option<&TokenSource> {
+        self.token_sources.get(&token.location())
+    }
 }
 
 #[derive(Clone)]
 struct CharLocationScanner<'a> {
+    contents: &'a str,
     cur_location: Location,
     cur_char: Option<char>,
     chars: Chars<'a>,
@@ -81,12 +219,22 @@ impl<'a> CharLocationScanner<'a> {
     fn new(contents: &'a str) -> CharLocationScanner<'a> {
         let mut chars = contents.chars();
         CharLocationScanner {
+            contents,
             cur_location: Location { line: 1, column: 1 },
             cur_char: chars.next(),
             chars,
         }
     }
 
+    /// The byte offset of the current char in the scanned text.
+    fn byte_offset(&self) -> usize {
+        self.contents.len() - self.chars.as_str().len() - self.cur_char_len()
+    }
+
+    fn cur_char_len(&self) -> usize {
+        self.cur_char.map_or(0, char::len_utf8)
+    }
+
     const fn current_char(&self) -> Option<char> {
         self.cur_char
     }
@@ -95,78 +243,412 @@ impl<'a> CharLocationScanner<'a> {
         self.cur_location
     }
 
+    fn next_char(&self) -> Option<char> {
+        self.chars.clone().next()
+    }
+
+    /// The current char and the ones after it, without advancing.
+    fn remaining_chars(&self) -> impl Iterator<Item = char> + 'a {
+        self.cur_char.into_iter().chain(self.chars.clone())
+    }
+
     fn current_char_and_location(&self) -> Option<(char, Location)> {
         self.cur_char.map(|c| (c, self.cur_location))
     }
 
+    /// Moves to the next char. Like `str::lines`, a `\r\n` is a single line
+    /// break whose `\n` is at the column of the `\r`, but a lone `\r` isn't
+    /// one.
     fn advance(&mut self) {
-        if self.cur_char == Some('\n') {
-            self.cur_location.line += 1;
-            self.cur_location.column = 0;
+        match self.cur_char {
+            Some('\n') => {
+                self.cur_location.line += 1;
+                self.cur_location.column = 1;
+            }
+            Some('\r') if self.next_char() == Some('\n') => (),
+            _ => self.cur_location.column += 1,
         }
-        self.cur_location.column += 1;
         self.cur_char = self.chars.next();
     }
 }
 
+/// The location of the char at the given byte offset of `source`, counted the
+/// same way as the locations of tokens, so a tab is a single column. An offset
+/// inside a char gives the location of that char, and offsets at or past the
+/// end give the location just after the last char.
+pub fn byte_to_location(source: &str, offset: usize) -> Location {
+    let mut chars = CharLocationScanner::new(source);
+    while chars.current_char().is_some() && chars.byte_offset() + chars.cur_char_len() <= offset {
+        chars.advance();
+    }
+    chars.current_location()
+}
+
 pub fn tokenize_text(contents: &str) -> Result<(Vec<Token>, LiteralData), TokenizingError> {
-    let mut chars = CharLocationScanner::new(contents);
+    tokenize_text_with_options(contents, &TokenizerOptions::default())
+}
+
+pub fn tokenize_text_with_options(
+    contents: &str,
+    options: &TokenizerOptions,
+) -> Result<(Vec<Token>, LiteralData), TokenizingError> {
+    let mut output = tokenize_text_recovering(contents, options);
+    if output.errors.is_empty() {
+        Ok((output.tokens, output.literal_data))
+    } else {
+        Err(output.errors.swap_remove(0))
+    }
+}
+
+/// Tokenizes the whole input, skipping over anything that fails to tokenize
+/// instead of stopping at the first error.
+pub fn tokenize_text_recovering(contents: &str, options: &TokenizerOptions) -> TokenizerOutput {
+    let mut tokenizer = Tokenizer::new(contents, options.clone());
     let mut tokens = Vec::new();
-    let mut identifiers = HashMap::new();
-    let mut string_literals = HashMap::new();
-    let mut integer_literals = HashMap::new();
+    let mut errors = Vec::new();
+    for result in &mut tokenizer {
+        match result {
+            Ok(token) => tokens.push(token),
+            Err(error) => errors.push(error),
+        }
+    }
+    let (literal_data, warnings) = tokenizer.finish();
 
-    while let Some((c, location)) = chars.current_char_and_location() {
-        let token_kind = match c {
-            _ if c.is_whitespace() => {
-                chars.advance();
-                continue;
-            }
-            '#' => {
-                while let Some(c) = chars.current_char() {
-                    chars.advance();
-                    if c == '\n' {
-                        break;
+    TokenizerOutput {
+        tokens,
+        literal_data,
+        errors,
+        warnings,
+    }
+}
+
+/// Tokenizes the input one token at a time, ending with an `EndOfFile`
+/// token. Like `tokenize_text_recovering`, tokenizing continues after an
+/// error. The data of the literals is collected along the way and is returned
+/// by `finish`.
+pub struct Tokenizer<'a> {
+    contents: &'a str,
+    chars: CharLocationScanner<'a>,
+    options: TokenizerOptions,
+    literal_data: LiteralData,
+    warnings: Vec<TokenizingWarning>,
+    trivia_start: usize,
+    finished: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(contents: &'a str, options: TokenizerOptions) -> Self {
+        let mut chars = CharLocationScanner::new(contents);
+        if options.skip_shebang && contents.starts_with("#!") {
+            skip_line(&mut chars);
+        }
+
+        Self {
+            contents,
+            chars,
+            options,
+            literal_data: LiteralData::default(),
+            warnings: Vec::new(),
+            trivia_start: 0,
+            finished: false,
+        }
+    }
+
+    /// The data of the literals tokenized so far.
+    pub const fn literal_data(&self) -> &LiteralData {
+        &self.literal_data
+    }
+
+    pub fn finish(self) -> (LiteralData, Vec<TokenizingWarning>) {
+        (self.literal_data, self.warnings)
+    }
+
+    fn record_source(&mut self, location: Location, start: usize, end: usize) {
+        let source = TokenSource {
+            leading_trivia: self.contents[self.trivia_start..start].to_string(),
+            spelling: self.contents[start..end].to_string(),
+        };
+        self.literal_data.token_sources.insert(location, source);
+        self.trivia_start = end;
+    }
+}
+
+impl Iterator for Tokenizer<'_> {
+    type Item = Result<Token, TokenizingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        while self.chars.current_char().is_some() {
+            let location = self.chars.current_location();
+            let start = self.chars.byte_offset();
+            let result = tokenize_token(&mut self.chars, &mut self.literal_data, &self.options);
+            // Every outcome, errors included, must consume input, so that even
+            // garbage input can't make the tokenizer loop forever.
+            debug_assert!(
+                self.chars.byte_offset() > start,
+                "ICE: tokenizing made no progress at {:?}",
+                location
+            );
+            match result {
+                Ok(Some(token_kind)) => {
+                    let span = Span {
+                        start: location,
+                        end: self.chars.current_location(),
+                    };
+                    let token = Token::new(token_kind, span);
+                    if self.options.warn_leading_zeros
+                        && self
+                            .literal_data
+                            .try_get_integer_literal(&token)
+                            .or_else(|| self.literal_data.try_get_float_literal(&token))
+                            .is_some_and(|text| has_redundant_leading_zero(text))
+                    {
+                        self.warnings
+                            .push(TokenizingWarning::RedundantLeadingZero { location });
                     }
-                }
-                continue;
-            }
-            _ if c.is_xid_start() => {
-                let s = tokenize_identifier_or_keyword(&mut chars);
-                match s.as_str() {
-                    "fn" => TokenKind::FunctionDefinition,
-                    "mut" => TokenKind::Mutable,
-                    "struct" => TokenKind::Struct,
-                    _ => {
-                        identifiers.insert(location, s);
-                        TokenKind::Identifier
+                    if self.options.keep_trivia {
+                        self.record_source(location, start, self.chars.byte_offset());
                     }
+                    return Some(Ok(token));
                 }
+                Ok(None) => (),
+                Err(error) => return Some(Err(error)),
             }
-            '0'..='9' => {
-                integer_literals.insert(location, tokenize_integer(&mut chars)?);
-                TokenKind::IntegerLiteral
+        }
+
+        self.finished = true;
+        let location = self.chars.current_location();
+        if self.options.keep_trivia {
+            let end = self.contents.len();
+            self.record_source(location, end, end);
+        }
+        let span = Span {
+            start: location,
+            end: location,
+        };
+        Some(Ok(Token::new(TokenKind::EndOfFile, span)))
+    }
+}
+
+/// Whether the text of a number literal starts with a zero followed by
+/// another digit. Base prefixes like `0x` have a letter after the zero.
+fn has_redundant_leading_zero(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next() == Some('0') && chars.next().is_some_and(|c| c.is_ascii_digit())
+}
+
+/// Skips the tokens that carry no meaning for parsing, such as `EndOfFile`.
+pub fn significant_tokens(tokens: &[Token]) -> impl Iterator<Item = &Token> {
+    tokens.iter().filter(|token| !token.kind().is_trivia())
+}
+
+/// Like `significant_tokens`, but takes ownership of the tokens.
+pub fn into_significant_tokens(tokens: Vec<Token>) -> impl Iterator<Item = Token> {
+    tokens.into_iter().filter(|token| !token.kind().is_trivia())
+}
+
+/// Whether the tokens could be a whole input, meaning that every opened
+/// bracket has been closed. This is much cheaper than parsing and lets an
+/// interactive prompt decide whether to keep reading lines. Unterminated
+/// strings and comments are reported by the tokenizer instead.
+///
+/// Closing brackets that were never opened count as complete, so that the
+/// parser gets to report them.
+pub fn is_complete(tokens: &[Token]) -> bool {
+    let mut depth: usize = 0;
+    for token in tokens {
+        match token.kind() {
+            TokenKind::OpenBraces | TokenKind::OpenParentheses | TokenKind::OpenBrackets => {
+                depth += 1
             }
-            '"' => {
-                string_literals.insert(location, tokenize_string(&mut chars)?);
-                TokenKind::StringLiteral
+            TokenKind::CloseBraces | TokenKind::CloseParentheses | TokenKind::CloseBrackets => {
+                match depth.checked_sub(1) {
+                    Some(new_depth) => depth = new_depth,
+                    None => return true,
+                }
             }
-            _ => tokenize_other_token(&mut chars).ok_or(TokenizingError {
-                location,
-                kind: TokenizingErrorKind::UnknownToken,
-            })?,
-        };
+            _ => (),
+        }
+    }
+    depth == 0
+}
+
+/// Returns the text of an identifier, literal or label token, the suffix of an
+/// integer literal included, or `None` for any other token.
+fn literal_text(token: &Token, data: &LiteralData) -> Option<String> {
+    data.try_get_identifier(token)
+        .or_else(|| data.try_get_float_literal(token))
+        .or_else(|| data.try_get_string_literal(token))
+        .or_else(|| data.try_get_label(token))
+        .cloned()
+        .or_else(|| {
+            data.try_get_integer_literal(token).map(|digits| {
+                let suffix = data.try_get_integer_suffix(token);
+                digits.clone() + suffix.map_or("", String::as_str)
+            })
+        })
+        .or_else(|| data.try_get_char_literal(token).map(String::from))
+        .or_else(|| data.try_get_bool_literal(token).map(|b| b.to_string()))
+}
+
+/// Renders a token for debug output, like `Identifier("foo") @ 1:1` or
+/// `Plus @ 2:3`.
+pub fn describe_token(token: &Token, data: &LiteralData) -> String {
+    let text = literal_text(token, data).map(|text| match token.kind() {
+        TokenKind::BoolLiteral => text,
+        TokenKind::CharLiteral => format!(
+            "{:?}",
+            text.chars()
+                .next()
+                .expect("ICE: char literals hold one char")
+        ),
+        _ => format!("{:?}", text),
+    });
+
+    let location = token.location();
+    match text {
+        Some(text) => format!(
+            "{:?}({}) @ {}:{}",
+            token.kind(),
+            text,
+            location.line,
+            location.column
+        ),
+        None => format!("{:?} @ {}:{}", token.kind(), location.line, location.column),
+    }
+}
+
+/// Serializes tokens as a JSON array of objects like
+/// `{"kind":"Identifier","span":{...},"text":"foo"}`. The text of identifiers,
+/// literals and labels is inlined since it isn't in the tokens themselves.
+#[cfg(feature = "serde")]
+pub fn tokens_to_json(tokens: &[Token], data: &LiteralData) -> String {
+    #[derive(serde::Serialize)]
+    struct JsonToken {
+        kind: TokenKind,
+        span: Span,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+    }
+
+    let tokens: Vec<_> = tokens
+        .iter()
+        .map(|token| JsonToken {
+            kind: token.kind(),
+            span: token.span(),
+            text: literal_text(token, data),
+        })
+        .collect();
+    serde_json::to_string(&tokens).expect("ICE: tokens can always be serialized")
+}
 
-        tokens.push(Token::new(token_kind, location));
+/// Counts how many tokens of each kind there are.
+pub fn token_histogram(tokens: &[Token]) -> HashMap<TokenKind, usize> {
+    let mut histogram = HashMap::new();
+    for token in tokens {
+        *histogram.entry(token.kind()).or_insert(0) += 1;
     }
+    histogram
+}
+
+/// Consumes the next token, or whitespace or a comment, in which case `None`
+/// is returned. Always consumes at least one character, even on error.
+fn tokenize_token(
+    chars: &mut CharLocationScanner,
+    literal_data: &mut LiteralData,
+    options: &TokenizerOptions,
+) -> Result<Option<TokenKind>, TokenizingError> {
+    let (c, location) = chars
+        .current_char_and_location()
+        .expect("ICE: tokenizing past the end of input");
 
-    let literal_data = LiteralData {
-        identifiers,
-        integer_literals,
-        string_literals,
+    let token_kind = match c {
+        _ if c.is_whitespace() => {
+            chars.advance();
+            return Ok(None);
+        }
+        '#' if options.hash == HashRole::Comment => {
+            skip_line(chars);
+            return Ok(None);
+        }
+        '/' if chars.next_char() == Some('*') => {
+            tokenize_block_comment(chars)?;
+            return Ok(None);
+        }
+        '_' if !chars.next_char().is_some_and(UnicodeXID::is_xid_continue) => {
+            chars.advance();
+            TokenKind::Underscore
+        }
+        _ if c.is_xid_start() || c == '_' => {
+            let s = tokenize_identifier_or_keyword(chars);
+            match keyword_for(&s) {
+                Some(TokenKind::BoolLiteral) => {
+                    literal_data.bool_literals.insert(location, s == "true");
+                    TokenKind::BoolLiteral
+                }
+                Some(keyword) => keyword,
+                None => {
+                    literal_data.identifiers.insert(location, s);
+                    TokenKind::Identifier
+                }
+            }
+        }
+        '0'..='9' => {
+            let number = tokenize_number(chars)?;
+            if let Some(suffix) = number.suffix {
+                literal_data.integer_suffixes.insert(location, suffix);
+            }
+            match number.kind {
+                TokenKind::FloatLiteral => {
+                    literal_data.float_literals.insert(location, number.text)
+                }
+                _ => literal_data.integer_literals.insert(location, number.text),
+            };
+            number.kind
+        }
+        '"' => {
+            let string = tokenize_string(chars)?;
+            literal_data.string_literals.insert(location, string);
+            TokenKind::StringLiteral
+        }
+        '\'' => {
+            chars.advance();
+            // `'a'` is a character literal but `'a` is a label
+            let is_label = chars.current_char().is_some_and(UnicodeXID::is_xid_start)
+                && chars.next_char() != Some('\'');
+            if is_label {
+                let label = tokenize_identifier_or_keyword(chars);
+                if chars.current_char() == Some('\'') {
+                    chars.advance();
+                    return Err(TokenizingError {
+                        location,
+                        kind: TokenizingErrorKind::InvalidCharLiteral,
+                    });
+                }
+                literal_data.labels.insert(location, label);
+                TokenKind::Label
+            } else {
+                let c = tokenize_char(chars, location)?;
+                literal_data.char_literals.insert(location, c);
+                TokenKind::CharLiteral
+            }
+        }
+        _ => match tokenize_other_token(chars) {
+            Some(token_kind) => token_kind,
+            None => {
+                chars.advance();
+                return Err(TokenizingError {
+                    location,
+                    kind: TokenizingErrorKind::UnknownToken,
+                });
+            }
+        },
     };
 
-    Ok((tokens, literal_data))
+    Ok(Some(token_kind))
 }
 
 fn tokenize_identifier_or_keyword(chars: &mut CharLocationScanner) -> String {
@@ -175,7 +657,7 @@ fn tokenize_identifier_or_keyword(chars: &mut CharLocationScanner) -> String {
     let mut token_chars = String::new();
     while chars
         .current_char()
-        .map_or(false, UnicodeXID::is_xid_continue)
+        .is_some_and(UnicodeXID::is_xid_continue)
     {
         token_chars.push(chars.current_char().unwrap());
         chars.advance();
@@ -184,20 +666,193 @@ fn tokenize_identifier_or_keyword(chars: &mut CharLocationScanner) -> String {
     token_chars
 }
 
-fn tokenize_integer(chars: &mut CharLocationScanner) -> Result<String, TokenizingError> {
+/// A number literal as scanned by `scan_number`.
+struct NumberLiteral {
+    kind: TokenKind,
+    /// The digits without separators, including any base prefix like `0x`.
+    text: String,
+    /// Only integers can have a suffix.
+    suffix: Option<String>,
+}
+
+fn tokenize_number(chars: &mut CharLocationScanner) -> Result<NumberLiteral, TokenizingError> {
+    scan_number(chars).inspect_err(|_| {
+        // skip the rest of the literal so tokenizing can resume after it
+        tokenize_identifier_or_keyword(chars);
+    })
+}
+
+/// Scans the parts of a number literal in order: an optional base prefix, the
+/// digits, a fraction and exponent for decimal numbers, and a suffix.
+fn scan_number(chars: &mut CharLocationScanner) -> Result<NumberLiteral, TokenizingError> {
     assert!(matches!(chars.current_char(), Some('0'..='9')));
 
-    let mut digits = String::new();
+    let start = chars.current_location();
+    let mut text = String::new();
+    let mut token_kind = TokenKind::IntegerLiteral;
+
+    let radix = match (chars.current_char(), chars.next_char()) {
+        (Some('0'), Some(prefix @ ('x' | 'b' | 'o'))) => {
+            chars.advance();
+            chars.advance();
+            text.push('0');
+            text.push(prefix);
+            match prefix {
+                'x' => 16,
+                'b' => 2,
+                _ => 8,
+            }
+        }
+        _ => 10,
+    };
+
+    // an underscore is accepted here only to report it as a misplaced separator
+    if !chars
+        .current_char()
+        .is_some_and(|c| c.is_digit(radix) || c == '_')
+    {
+        return Err(TokenizingError {
+            location: chars.current_location(),
+            kind: TokenizingErrorKind::InvalidDigit,
+        });
+    }
+
+    tokenize_digit_group(chars, &mut text, radix)?;
+
+    if radix == 10 {
+        token_kind = scan_fraction_and_exponent(chars, &mut text)?;
+    }
+
+    let suffix = match chars.current_char() {
+        Some(c) if c.is_xid_start() => {
+            let location = chars.current_location();
+            let suffix = tokenize_identifier_or_keyword(chars);
+            let max = INTEGER_SUFFIXES
+                .iter()
+                .find(|(name, _)| *name == suffix)
+                .filter(|_| token_kind == TokenKind::IntegerLiteral)
+                .map(|(_, max)| *max)
+                .ok_or(TokenizingError {
+                    location,
+                    kind: TokenizingErrorKind::InvalidSuffix,
+                })?;
+            if !integer_value(&text).is_ok_and(|value| value <= max) {
+                return Err(TokenizingError {
+                    location: start,
+                    kind: TokenizingErrorKind::IntegerOutOfRange,
+                });
+            }
+            Some(suffix)
+        }
+        _ => None,
+    };
+
+    Ok(NumberLiteral {
+        kind: token_kind,
+        text,
+        suffix,
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntegerParseError {
+    /// There are no digits, like in `0x` or `_`.
+    Empty,
+    InvalidDigit,
+    /// The value doesn't fit in a `u64`.
+    Overflow,
+}
+
+/// The value of the text of an integer literal, ignoring `_` separators and
+/// honoring a `0x`, `0b` or `0o` prefix.
+fn integer_value(text: &str) -> Result<u64, IntegerParseError> {
+    let (digits, radix) = match text.get(..2) {
+        Some("0x") => (&text[2..], 16),
+        Some("0b") => (&text[2..], 2),
+        Some("0o") => (&text[2..], 8),
+        _ => (text, 10),
+    };
+
+    let mut value: Option<u64> = None;
+    for c in digits.chars().filter(|&c| c != '_') {
+        let digit = c.to_digit(radix).ok_or(IntegerParseError::InvalidDigit)?;
+        value = Some(
+            value
+                .unwrap_or(0)
+                .checked_mul(radix.into())
+                .and_then(|value| value.checked_add(digit.into()))
+                .ok_or(IntegerParseError::Overflow)?,
+        );
+    }
+    value.ok_or(IntegerParseError::Empty)
+}
+
+/// Scans the optional fraction and exponent after the integer part of a
+/// decimal number, returning whether the number is a float.
+fn scan_fraction_and_exponent(
+    chars: &mut CharLocationScanner,
+    text: &mut String,
+) -> Result<TokenKind, TokenizingError> {
+    let mut token_kind = TokenKind::IntegerLiteral;
+
+    if chars.current_char() == Some('.') && matches!(chars.next_char(), Some('0'..='9' | '_')) {
+        chars.advance();
+        text.push('.');
+        tokenize_digit_group(chars, text, 10)?;
+        token_kind = TokenKind::FloatLiteral;
+    }
+
+    if let Some((c @ ('e' | 'E'), location)) = chars.current_char_and_location() {
+        chars.advance();
+        text.push(c);
+        if let Some(sign @ ('+' | '-')) = chars.current_char() {
+            chars.advance();
+            text.push(sign);
+        }
+        if !matches!(chars.current_char(), Some('0'..='9' | '_')) {
+            return Err(TokenizingError {
+                location,
+                kind: TokenizingErrorKind::MissingExponent,
+            });
+        }
+        tokenize_digit_group(chars, text, 10)?;
+        token_kind = TokenKind::FloatLiteral;
+    }
+
+    Ok(token_kind)
+}
+
+/// Consumes a run of digits in the given radix, which may be separated by
+/// single underscores, and pushes the digits without the separators to
+/// `text`. Outside of decimal, a letter that isn't a digit is an error,
+/// except for the `i` or `u` that starts a suffix.
+fn tokenize_digit_group(
+    chars: &mut CharLocationScanner,
+    text: &mut String,
+    radix: u32,
+) -> Result<(), TokenizingError> {
+    let invalid_separator = |chars: &CharLocationScanner| TokenizingError {
+        location: chars.current_location(),
+        kind: TokenizingErrorKind::InvalidSeparator,
+    };
+
+    // a group can't start with a separator
+    if chars.current_char() == Some('_') {
+        return Err(invalid_separator(chars));
+    }
 
-    // TODO: suffixes
     while let Some(c) = chars.current_char() {
         match c {
-            '0'..='9' => digits.push(c),
+            _ if c.is_digit(radix) => text.push(c),
+            // or end with one, or have two in a row
+            '_' if !chars.next_char().is_some_and(|c| c.is_digit(radix)) => {
+                return Err(invalid_separator(chars))
+            }
             '_' => (),
-            'A'..='Z' | 'a'..='z' => {
+            _ if radix != 10 && c.is_ascii_alphanumeric() && !matches!(c, 'i' | 'u') => {
                 return Err(TokenizingError {
                     location: chars.current_location(),
-                    kind: TokenizingErrorKind::InvalidSuffix,
+                    kind: TokenizingErrorKind::InvalidDigit,
                 })
             }
             _ => break,
@@ -206,83 +861,285 @@ fn tokenize_integer(chars: &mut CharLocationScanner) -> Result<String, Tokenizin
         chars.advance();
     }
 
-    Ok(digits)
+    Ok(())
+}
+
+/// Consumes everything up to and including the next newline.
+fn skip_line(chars: &mut CharLocationScanner) {
+    while let Some(c) = chars.current_char() {
+        chars.advance();
+        if c == '\n' {
+            break;
+        }
+    }
+}
+
+/// Consumes a block comment, reporting an unterminated one at its opening
+/// `/*`. Block comments nest, so `/* a /* b */ c */` is a single comment.
+fn tokenize_block_comment(chars: &mut CharLocationScanner) -> Result<(), TokenizingError> {
+    let location = chars.current_location();
+    chars.advance();
+    chars.advance();
+
+    let mut depth = 1;
+    while let Some(c) = chars.current_char() {
+        chars.advance();
+        match (c, chars.current_char()) {
+            ('*', Some('/')) => {
+                chars.advance();
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(());
+                }
+            }
+            ('/', Some('*')) => {
+                chars.advance();
+                depth += 1;
+            }
+            _ => (),
+        }
+    }
+
+    Err(TokenizingError {
+        location,
+        kind: TokenizingErrorKind::UnterminatedComment,
+    })
 }
 
 fn tokenize_string(chars: &mut CharLocationScanner) -> Result<String, TokenizingError> {
     assert_eq!(chars.current_char(), Some('"'));
+    let location = chars.current_location();
     chars.advance();
 
     let mut string = String::new();
+    // the rest of the string is still consumed after an error, so that
+    // tokenizing can resume after the closing quote
+    let mut error = None;
     while let Some(c) = chars.current_char() {
-        string.push(match c {
-            '\\' => {
-                chars.advance();
-                match chars.current_char() {
-                    Some('"') => '"',
-                    Some('\\') => '\\',
-                    Some('n') => '\n',
-                    Some('r') => '\r',
-                    Some('t') => '\t',
-                    _ => {
-                        return Err(TokenizingError {
-                            location: chars.current_location(),
-                            kind: TokenizingErrorKind::InvalidEscape,
-                        })
-                    }
+        match c {
+            '\\' => match tokenize_escape(chars) {
+                Ok(c) => string.push(c),
+                Err(e) => {
+                    error.get_or_insert(e);
                 }
-            }
+            },
             '"' => {
                 chars.advance();
-                break;
+                return match error {
+                    Some(error) => Err(error),
+                    None => Ok(string),
+                };
             }
-            _ => c,
-        });
-        chars.advance();
+            _ => {
+                string.push(c);
+                chars.advance();
+            }
+        }
     }
-    Ok(string)
+
+    Err(TokenizingError {
+        location,
+        kind: TokenizingErrorKind::UnterminatedString,
+    })
 }
 
-fn tokenize_other_token(chars: &mut CharLocationScanner) -> Option<TokenKind> {
-    // TODO: use stackvec or something
-    let mut cur_chars = Vec::new();
-    let mut cur_candidate = None;
-    let mut continue_chars = chars.clone();
-    while let Some(entry) = TOKEN_MAP.get(&cur_chars[..]) {
-        if let Some(new_candidate) = entry {
-            cur_candidate = Some(*new_candidate);
-            continue_chars = chars.clone();
+/// Tokenizes the contents of a character literal, the opening quote of which
+/// was at `location` and has already been consumed.
+fn tokenize_char(
+    chars: &mut CharLocationScanner,
+    location: Location,
+) -> Result<char, TokenizingError> {
+    let invalid_char_literal = TokenizingError {
+        location,
+        kind: TokenizingErrorKind::InvalidCharLiteral,
+    };
+
+    let c = match chars.current_char() {
+        Some('\\') => tokenize_escape(chars)?,
+        Some(c) if c != '\'' && c != '\n' => {
+            chars.advance();
+            c
         }
+        _ => return Err(invalid_char_literal),
+    };
 
-        match chars.current_char() {
-            Some(c) => cur_chars.push(c),
-            None => break,
+    if chars.current_char() != Some('\'') {
+        return Err(invalid_char_literal);
+    }
+    chars.advance();
+
+    Ok(c)
+}
+
+/// Consumes an escape sequence starting with a backslash, returning the
+/// character it stands for. An invalid escape character is consumed too, but
+/// see `tokenize_unicode_escape` for `\u{...}`.
+fn tokenize_escape(chars: &mut CharLocationScanner) -> Result<char, TokenizingError> {
+    assert_eq!(chars.current_char(), Some('\\'));
+    chars.advance();
+
+    let c = match chars.current_char() {
+        Some('"') => '"',
+        Some('\'') => '\'',
+        Some('\\') => '\\',
+        Some('n') => '\n',
+        Some('r') => '\r',
+        Some('t') => '\t',
+        Some('0') => '\0',
+        Some('u') => return tokenize_unicode_escape(chars),
+        _ => {
+            let error = TokenizingError {
+                location: chars.current_location(),
+                kind: TokenizingErrorKind::InvalidEscape,
+            };
+            if chars.current_char().is_some() {
+                chars.advance();
+            }
+            return Err(error);
         }
+    };
+    chars.advance();
 
-        chars.advance();
+    Ok(c)
+}
+
+/// Consumes the `u{...}` of a unicode escape, with one to six hex digits
+/// that must be a unicode scalar value. On error, the char at the error is
+/// not consumed so that a closing quote still ends the literal.
+fn tokenize_unicode_escape(chars: &mut CharLocationScanner) -> Result<char, TokenizingError> {
+    assert_eq!(chars.current_char(), Some('u'));
+    chars.advance();
+
+    let invalid_escape = |chars: &CharLocationScanner| TokenizingError {
+        location: chars.current_location(),
+        kind: TokenizingErrorKind::InvalidEscape,
+    };
+
+    if chars.current_char() != Some('{') {
+        return Err(invalid_escape(chars));
+    }
+    chars.advance();
+
+    let digits_location = chars.current_location();
+    let mut value: u32 = 0;
+    let mut digit_count = 0;
+    loop {
+        match chars.current_char() {
+            Some('}') if digit_count > 0 => break,
+            Some(c) if digit_count < 6 && c.is_ascii_hexdigit() => {
+                value = value * 16 + c.to_digit(16).unwrap();
+                digit_count += 1;
+                chars.advance();
+            }
+            _ => return Err(invalid_escape(chars)),
+        }
     }
+    chars.advance();
+
+    // above 0x10FFFF or a surrogate
+    char::from_u32(value).ok_or(TokenizingError {
+        location: digits_location,
+        kind: TokenizingErrorKind::InvalidEscape,
+    })
+}
+
+/// Consumes the longest operator or punctuation token at the current char.
+/// Only prefixes of tokens are in `TOKEN_MAP`, so this looks at most as many
+/// chars ahead as the longest token has, whatever comes after.
+fn tokenize_other_token(chars: &mut CharLocationScanner) -> Option<TokenKind> {
+    let mut prefix = ['\0'; LONGEST_OTHER_TOKEN];
+    let mut prefix_len = 0;
+    let mut accepted = None;
+    let mut lookahead = chars.remaining_chars();
+    while let Some(entry) = TOKEN_MAP.get(&prefix[..prefix_len]) {
+        if let Some(token_kind) = entry {
+            accepted = Some((*token_kind, prefix_len));
+        }
 
-    *chars = continue_chars;
+        match lookahead.next() {
+            Some(c) if prefix_len < LONGEST_OTHER_TOKEN => prefix[prefix_len] = c,
+            _ => break,
+        }
+        prefix_len += 1;
+    }
 
-    cur_candidate
+    let (token_kind, len) = accepted?;
+    for _ in 0..len {
+        chars.advance();
+    }
+    Some(token_kind)
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TokenizingError {
     pub location: Location,
     pub kind: TokenizingErrorKind,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum TokenizingErrorKind {
     InvalidSuffix,
     InvalidEscape,
+    InvalidCharLiteral,
+    InvalidSeparator,
+    InvalidDigit,
+    MissingExponent,
     UnknownToken,
+    /// Located at the opening quote.
+    UnterminatedString,
+    /// Located at the opening `/*`.
+    UnterminatedComment,
+    /// An integer literal too large for the type of its suffix, located at the
+    /// start of the literal.
+    IntegerOutOfRange,
+}
+
+impl TokenizingErrorKind {
+    /// A short code identifying the kind of error, see `DESIGN.md`. Codes must
+    /// never change or be reused, new kinds get the next free code.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidEscape => "E0001",
+            Self::InvalidSuffix => "E0002",
+            Self::UnknownToken => "E0003",
+            Self::InvalidSeparator => "E0004",
+            Self::MissingExponent => "E0005",
+            Self::InvalidCharLiteral => "E0006",
+            Self::InvalidDigit => "E0007",
+            Self::UnterminatedString => "E0008",
+            Self::UnterminatedComment => "E0009",
+            Self::IntegerOutOfRange => "E0010",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TokenizingWarning {
+    RedundantLeadingZero { location: Location },
+}
+
+impl TokenizingWarning {
+    /// Like the error codes, see `DESIGN.md`.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::RedundantLeadingZero { .. } => "W0001",
+        }
+    }
+
+    pub const fn location(&self) -> Location {
+        match self {
+            Self::RedundantLeadingZero { location } => *location,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, BuildHasherDefault};
+
     use super::*;
+    use crate::token::LocationHasher;
 
     #[test]
     fn test() {
@@ -292,6 +1149,7 @@ mod tests {
             TokenKind::Comma,
             TokenKind::Identifier,
             TokenKind::Not,
+            TokenKind::EndOfFile,
         ];
 
         assert!(tokenize_text(input)
@@ -302,6 +1160,81 @@ mod tests {
             .eq(output.into_iter()));
     }
 
+    /// A token from `start` up to `end`, both `(line, column)`.
+    fn token(kind: TokenKind, start: (usize, usize), end: (usize, usize)) -> Token {
+        let location = |(line, column)| Location { line, column };
+        Token::new(
+            kind,
+            Span {
+                start: location(start),
+                end: location(end),
+            },
+        )
+    }
+
+    #[test]
+    fn test_whole_tokens() {
+        let (tokens, _) = tokenize_text("a b").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                token(TokenKind::Identifier, (1, 1), (1, 2)),
+                token(TokenKind::Identifier, (1, 3), (1, 4)),
+                token(TokenKind::EndOfFile, (1, 4), (1, 4)),
+            ]
+        );
+
+        let (tokens, _) = tokenize_text("x\n  <= 1.5").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                token(TokenKind::Identifier, (1, 1), (1, 2)),
+                token(TokenKind::LessOrEqual, (2, 3), (2, 5)),
+                token(TokenKind::FloatLiteral, (2, 6), (2, 9)),
+                token(TokenKind::EndOfFile, (2, 9), (2, 9)),
+            ]
+        );
+        assert_ne!(tokens[0], token(TokenKind::Identifier, (1, 1), (1, 3)));
+    }
+
+    #[test]
+    fn test_tokenize_program() {
+        let (tokens, literal_data) = tokenize_text(include_str!("../example.txt")).unwrap();
+        assert!(tokens.len() > 1);
+        assert!(tokens
+            .iter()
+            .any(|token| literal_data.try_get_identifier(token).is_some()));
+        assert_eq!(tokens.last().map(Token::kind), Some(TokenKind::EndOfFile));
+    }
+
+    #[test]
+    fn test_streaming() {
+        let mut tokenizer = Tokenizer::new("hello, world!", TokenizerOptions::default());
+        let tokens: Vec<_> = (&mut tokenizer).map(Result::unwrap).collect();
+        assert_eq!(
+            tokens.iter().map(Token::kind).collect::<Vec<_>>(),
+            &[
+                TokenKind::Identifier,
+                TokenKind::Comma,
+                TokenKind::Identifier,
+                TokenKind::Not,
+                TokenKind::EndOfFile,
+            ]
+        );
+        assert!(tokenizer.next().is_none());
+
+        let (literal_data, _) = tokenizer.finish();
+        assert_eq!(
+            literal_data.try_get_identifier(&tokens[2]).unwrap(),
+            "world"
+        );
+
+        // errors don't end the stream
+        let results: Vec<_> = Tokenizer::new("a @ b", TokenizerOptions::default()).collect();
+        assert_eq!(results.len(), 4);
+        assert!(results[1].is_err());
+    }
+
     #[test]
     fn test3() {
         let input = "=:=:=";
@@ -309,6 +1242,7 @@ mod tests {
             TokenKind::Assign,
             TokenKind::DefineVar,
             TokenKind::DefineVar,
+            TokenKind::EndOfFile,
         ];
 
         assert!(tokenize_text(input)
@@ -329,7 +1263,7 @@ mod tests {
     #[test]
     fn test_new_line_between() {
         let input1 = "=\n=";
-        let expected_output1 = &[TokenKind::Assign, TokenKind::Assign];
+        let expected_output1 = &[TokenKind::Assign, TokenKind::Assign, TokenKind::EndOfFile];
         let output1 = tokenize_text(input1).unwrap();
         assert_eq!(
             output1.0.iter().map(Token::kind).collect::<Vec<_>>(),
@@ -337,11 +1271,979 @@ mod tests {
         );
 
         let input2 = "first\nsecond";
-        let expected_output2 = &[TokenKind::Identifier, TokenKind::Identifier];
+        let expected_output2 = &[
+            TokenKind::Identifier,
+            TokenKind::Identifier,
+            TokenKind::EndOfFile,
+        ];
         let output2 = tokenize_text(input2).unwrap();
         assert_eq!(
             output2.0.iter().map(Token::kind).collect::<Vec<_>>(),
             expected_output2
         );
     }
+
+    fn tokenize_single_literal(input: &str) -> (TokenKind, String) {
+        let (tokens, literal_data) = tokenize_text(input).unwrap();
+        assert_eq!(tokens.len(), 2);
+        let text = literal_data
+            .try_get_integer_literal(&tokens[0])
+            .or_else(|| literal_data.try_get_float_literal(&tokens[0]))
+            .or_else(|| literal_data.try_get_string_literal(&tokens[0]))
+            .unwrap();
+        (tokens[0].kind(), text.clone())
+    }
+
+    #[test]
+    fn test_float_separators() {
+        assert_eq!(
+            tokenize_single_literal("1_000.000_1"),
+            (TokenKind::FloatLiteral, "1000.0001".to_string())
+        );
+        assert_eq!(
+            tokenize_single_literal("1e1_0"),
+            (TokenKind::FloatLiteral, "1e10".to_string())
+        );
+        assert_eq!(
+            tokenize_single_literal("1_0"),
+            (TokenKind::IntegerLiteral, "10".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_separators() {
+        for (input, column) in [
+            ("1._5", 3),
+            ("1__0", 2),
+            ("1_", 2),
+            ("1.5_e3", 4),
+            ("1e_3", 3),
+        ] {
+            let error = tokenize_text(input).unwrap_err();
+            assert!(matches!(error.kind, TokenizingErrorKind::InvalidSeparator));
+            assert_eq!(error.location, Location { line: 1, column });
+        }
+    }
+
+    #[test]
+    fn test_char_literal_and_label() {
+        let (tokens, literal_data) = tokenize_text("'a' 'outer 'b: '\\''").unwrap();
+        assert_eq!(
+            tokens.iter().map(Token::kind).collect::<Vec<_>>(),
+            &[
+                TokenKind::CharLiteral,
+                TokenKind::Label,
+                TokenKind::Label,
+                TokenKind::FieldTypeSeparator,
+                TokenKind::CharLiteral,
+                TokenKind::EndOfFile,
+            ]
+        );
+        assert_eq!(literal_data.try_get_char_literal(&tokens[0]), Some('a'));
+        assert_eq!(
+            literal_data.try_get_label(&tokens[1]).map(String::as_str),
+            Some("outer")
+        );
+        assert_eq!(literal_data.try_get_char_literal(&tokens[4]), Some('\''));
+    }
+
+    #[test]
+    fn test_char_literal_edge_cases() {
+        let (tokens, literal_data) = tokenize_text(r#"'x' '\n' '\\' '\'' '"'"#).unwrap();
+        let chars: Vec<_> = tokens
+            .iter()
+            .filter_map(|token| literal_data.try_get_char_literal(token))
+            .collect();
+        assert_eq!(chars, ['x', '\n', '\\', '\'', '"']);
+
+        // `'a` on its own is a label, so only other unterminated literals are
+        // errors
+        for input in ["''", "'ab'", "'\\n", "'", "'1", "'\n'"] {
+            let error = tokenize_text(input).unwrap_err();
+            assert_eq!(
+                error.kind,
+                TokenizingErrorKind::InvalidCharLiteral,
+                "{input}"
+            );
+            assert_eq!(error.location, Location { line: 1, column: 1 });
+        }
+    }
+
+    #[test]
+    fn test_trivia_reconstructs_source() {
+        let input = "# comment\nfn f(x: i64) -> i64 {\r\n\ty := \"a\\\"b\" + 1_000 * 'c';  @\n}\n";
+        let options = TokenizerOptions {
+            keep_trivia: true,
+            ..TokenizerOptions::default()
+        };
+        let TokenizerOutput {
+            tokens,
+            literal_data,
+            errors,
+            ..
+        } = tokenize_text_recovering(input, &options);
+        assert_eq!(errors.len(), 1);
+
+        let mut output = String::new();
+        for token in &tokens {
+            let source = literal_data.try_get_token_source(token).unwrap();
+            output.push_str(&source.leading_trivia);
+            output.push_str(&source.spelling);
+        }
+
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_no_trivia_by_default() {
+        let (tokens, literal_data) = tokenize_text("a b").unwrap();
+        assert!(literal_data.try_get_token_source(&tokens[1]).is_none());
+    }
+
+    #[test]
+    fn test_end_of_file_location() {
+        for (input, line, column) in [("", 1, 1), ("a\nbc ", 2, 4), ("a\n", 2, 1)] {
+            let (tokens, _) = tokenize_text(input).unwrap();
+            let end = tokens.last().unwrap();
+            assert_eq!(end.kind(), TokenKind::EndOfFile);
+            assert_eq!(end.location(), Location { line, column }, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn test_line_starts() {
+        for (input, line, column) in [
+            ("\nx", 2, 1),
+            ("\n\nx", 3, 1),
+            ("a\nb\nc", 3, 1),
+            ("a\n\n  x", 3, 3),
+            ("a\n\tx", 2, 2),
+        ] {
+            let (tokens, _) = tokenize_text(input).unwrap();
+            let x = &tokens[tokens.len() - 2];
+            assert_eq!(x.location(), Location { line, column }, "{input:?}");
+            // the line of the location is what error messages print
+            let source_line = input.lines().nth(line - 1).unwrap();
+            assert!(source_line.chars().nth(column - 1).unwrap().is_alphabetic());
+        }
+
+        // a newline belongs to the line it ends
+        let (tokens, _) = tokenize_text("\"a\n\"").unwrap();
+        assert_eq!(tokens[1].location(), Location { line: 2, column: 2 });
+        let error = tokenize_text("a\n\n$").unwrap_err();
+        assert_eq!(error.location, Location { line: 3, column: 1 });
+    }
+
+    #[test]
+    fn test_crlf_line_endings() {
+        let (tokens, _) = tokenize_text("a\r\nb").unwrap();
+        assert_eq!(tokens[1].location(), Location { line: 2, column: 1 });
+
+        for (input, line, column) in [
+            ("a\r\n\r\n  x", 3, 3),
+            ("/* a\r\n b */ x", 2, 7),
+            ("\"a\r\nb\" x", 2, 4),
+            // a lone `\r` is whitespace on the same line, as in `str::lines`
+            ("a\rx", 1, 3),
+        ] {
+            let (tokens, _) = tokenize_text(input).unwrap();
+            let x = &tokens[tokens.len() - 2];
+            assert_eq!(x.location(), Location { line, column }, "{input:?}");
+        }
+
+        let options = TokenizerOptions {
+            hash: HashRole::Comment,
+            ..TokenizerOptions::default()
+        };
+        let (tokens, _) = tokenize_text_with_options("# comment\r\nx", &options).unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].location(), Location { line: 2, column: 1 });
+
+        let (tokens, literal_data) = tokenize_text("\"a\r\nb\"\r\n").unwrap();
+        assert_eq!(
+            literal_data.try_get_string_literal(&tokens[0]).unwrap(),
+            "a\r\nb"
+        );
+        assert_eq!(tokens[0].span().end, Location { line: 2, column: 3 });
+        assert_eq!(tokens[1].location(), Location { line: 3, column: 1 });
+
+        assert_eq!(
+            byte_to_location("a\r\nb", 1),
+            Location { line: 1, column: 2 }
+        );
+        assert_eq!(
+            byte_to_location("a\r\nb", 2),
+            Location { line: 1, column: 2 }
+        );
+        assert_eq!(
+            byte_to_location("a\r\nb", 3),
+            Location { line: 2, column: 1 }
+        );
+    }
+
+    #[test]
+    fn test_spans() {
+        let (tokens, _) = tokenize_text("hello <<= \"a\nb\"").unwrap();
+        let columns: Vec<_> = tokens
+            .iter()
+            .map(|token| {
+                let span = token.span();
+                (
+                    span.start.line,
+                    span.start.column,
+                    span.end.line,
+                    span.end.column,
+                )
+            })
+            .collect();
+        // `hello` covers columns 1 through 5, so its end is at column 6
+        assert_eq!(
+            columns,
+            [
+                (1, 1, 1, 6),
+                (1, 7, 1, 9),
+                (1, 9, 1, 10),
+                (1, 11, 2, 3),
+                (2, 3, 2, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_significant_tokens() {
+        let (tokens, _) = tokenize_text("a + b").unwrap();
+        assert_eq!(tokens.last().map(Token::kind), Some(TokenKind::EndOfFile));
+        assert_eq!(
+            significant_tokens(&tokens)
+                .map(Token::kind)
+                .collect::<Vec<_>>(),
+            &[
+                TokenKind::Identifier,
+                TokenKind::Plus,
+                TokenKind::Identifier
+            ]
+        );
+        assert_eq!(into_significant_tokens(tokens).count(), 3);
+    }
+
+    #[test]
+    fn test_is_complete() {
+        let complete = |input| is_complete(&tokenize_text(input).unwrap().0);
+        assert!(complete("{ x := 1; }"));
+        assert!(!complete("{ x := 1;"));
+        assert!(!complete("f(a[0]"));
+        assert!(complete(""));
+        assert!(complete("  # just a comment"));
+        assert!(complete("x }"));
+    }
+
+    #[test]
+    fn test_base_prefixes() {
+        for (input, text) in [("0xFF_ff", "0xFFff"), ("0b10_1", "0b101"), ("0o17", "0o17")] {
+            assert_eq!(
+                tokenize_single_literal(input),
+                (TokenKind::IntegerLiteral, text.to_string())
+            );
+        }
+
+        for (input, column) in [("0b2", 3), ("0o8", 3), ("0xG", 3), ("0b1012", 6), ("0x", 3)] {
+            let error = tokenize_text(input).unwrap_err();
+            assert!(matches!(error.kind, TokenizingErrorKind::InvalidDigit));
+            assert_eq!(error.location, Location { line: 1, column });
+        }
+    }
+
+    fn leading_zero_warnings(input: &str) -> Vec<TokenizingWarning> {
+        let options = TokenizerOptions {
+            warn_leading_zeros: true,
+            ..TokenizerOptions::default()
+        };
+        let output = tokenize_text_recovering(input, &options);
+        assert!(output.errors.is_empty());
+        output.warnings
+    }
+
+    #[test]
+    fn test_redundant_leading_zero() {
+        assert_eq!(
+            leading_zero_warnings("x 007"),
+            vec![TokenizingWarning::RedundantLeadingZero {
+                location: Location { line: 1, column: 3 }
+            }]
+        );
+        assert!(leading_zero_warnings("0").is_empty());
+        assert!(leading_zero_warnings("0x07 0b01 0o07 0.5 10").is_empty());
+        assert!(
+            tokenize_text_recovering("007", &TokenizerOptions::default())
+                .warnings
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let error = tokenize_text("x = \"hello").unwrap_err();
+        assert!(matches!(
+            error.kind,
+            TokenizingErrorKind::UnterminatedString
+        ));
+        assert_eq!(error.location, Location { line: 1, column: 5 });
+
+        let error = tokenize_text("\"a\\q\nb").unwrap_err();
+        assert!(matches!(
+            error.kind,
+            TokenizingErrorKind::UnterminatedString
+        ));
+        assert_eq!(error.location, Location { line: 1, column: 1 });
+
+        // an escaped quote or a lone backslash doesn't close the string
+        for input in ["x \"a\\\"", "x \"a\\"] {
+            let error = tokenize_text(input).unwrap_err();
+            assert_eq!(error.kind, TokenizingErrorKind::UnterminatedString);
+            assert_eq!(error.location, Location { line: 1, column: 3 });
+        }
+    }
+
+    #[test]
+    fn test_unicode_escapes() {
+        for (input, string) in [
+            (r#""\u{41}""#, "A"),
+            (r#""\u{1F600}!""#, "\u{1F600}!"),
+            (r#""\u{10FFFF}""#, "\u{10FFFF}"),
+            (r#""a\0b""#, "a\0b"),
+        ] {
+            assert_eq!(
+                tokenize_single_literal(input),
+                (TokenKind::StringLiteral, string.to_string())
+            );
+        }
+
+        for (input, column) in [
+            (r#""\u{110000}""#, 5),
+            (r#""\u{D800}""#, 5),
+            (r#""\u41""#, 4),
+            (r#""\u{}""#, 5),
+            (r#""\u{4G}""#, 6),
+            (r#""\u{41""#, 7),
+            (r#""\u{0000041}""#, 11),
+        ] {
+            let error = tokenize_text(input).unwrap_err();
+            assert_eq!(error.kind, TokenizingErrorKind::InvalidEscape, "{input}");
+            assert_eq!(error.location, Location { line: 1, column }, "{input}");
+        }
+
+        let (tokens, literal_data) = tokenize_text(r"'\u{e9}'").unwrap();
+        assert_eq!(literal_data.try_get_char_literal(&tokens[0]), Some('é'));
+    }
+
+    #[test]
+    fn test_block_comments() {
+        let (tokens, _) = tokenize_text("a /* b\n c */ / d").unwrap();
+        assert_eq!(
+            tokens.iter().map(Token::kind).collect::<Vec<_>>(),
+            &[
+                TokenKind::Identifier,
+                TokenKind::Div,
+                TokenKind::Identifier,
+                TokenKind::EndOfFile
+            ]
+        );
+
+        let error = tokenize_text("a\n  /* b\n c").unwrap_err();
+        assert!(matches!(
+            error.kind,
+            TokenizingErrorKind::UnterminatedComment
+        ));
+        assert_eq!(error.location, Location { line: 2, column: 3 });
+
+        let (tokens, _) = tokenize_text("/* a /* b */ c */ x /*/ y */").unwrap();
+        assert_eq!(
+            tokens.iter().map(Token::kind).collect::<Vec<_>>(),
+            &[TokenKind::Identifier, TokenKind::EndOfFile]
+        );
+
+        let error = tokenize_text("x /* a /* b */ c").unwrap_err();
+        assert_eq!(error.kind, TokenizingErrorKind::UnterminatedComment);
+        assert_eq!(error.location, Location { line: 1, column: 3 });
+    }
+
+    #[test]
+    fn test_hash_role() {
+        let kinds = |input, options: &TokenizerOptions| {
+            let output = tokenize_text_recovering(input, options);
+            assert!(output.errors.is_empty());
+            output.tokens.iter().map(Token::kind).collect::<Vec<_>>()
+        };
+
+        let comment = TokenizerOptions::default();
+        assert_eq!(kinds("#foo", &comment), &[TokenKind::EndOfFile]);
+
+        let token = TokenizerOptions {
+            hash: HashRole::Token,
+            ..TokenizerOptions::default()
+        };
+        assert_eq!(
+            kinds("#foo", &token),
+            &[TokenKind::Hash, TokenKind::Identifier, TokenKind::EndOfFile]
+        );
+
+        let shebang = TokenizerOptions {
+            hash: HashRole::Token,
+            skip_shebang: true,
+            ..TokenizerOptions::default()
+        };
+        assert_eq!(
+            kinds("#!/bin/run\n#foo", &shebang),
+            &[TokenKind::Hash, TokenKind::Identifier, TokenKind::EndOfFile]
+        );
+    }
+
+    #[test]
+    fn test_path_separator() {
+        let (tokens, _) = tokenize_text("use a::b: :").unwrap();
+        assert_eq!(
+            tokens.iter().map(Token::kind).collect::<Vec<_>>(),
+            &[
+                TokenKind::Use,
+                TokenKind::Identifier,
+                TokenKind::PathSep,
+                TokenKind::Identifier,
+                TokenKind::FieldTypeSeparator,
+                TokenKind::FieldTypeSeparator,
+                TokenKind::EndOfFile
+            ]
+        );
+    }
+
+    fn identifier_at(line: usize, column: usize) -> Token {
+        let location = Location { line, column };
+        Token::new(
+            TokenKind::Identifier,
+            Span {
+                start: location,
+                end: location,
+            },
+        )
+    }
+
+    #[test]
+    fn test_literals_keyed_by_location() {
+        let mut literal_data = LiteralData::default();
+        let first = identifier_at(1, 2);
+        let second = identifier_at(2, 1);
+        literal_data
+            .identifiers
+            .insert(first.location(), "a".to_string());
+        literal_data
+            .identifiers
+            .insert(second.location(), "b".to_string());
+
+        assert_eq!(literal_data.try_get_identifier(&first).unwrap(), "a");
+        assert_eq!(literal_data.try_get_identifier(&second).unwrap(), "b");
+        let swapped = identifier_at(2, 2);
+        assert_eq!(literal_data.try_get_identifier(&swapped), None);
+    }
+
+    #[test]
+    fn test_right_arrow() {
+        let (tokens, _) = tokenize_text("a -> b - > c->-d").unwrap();
+        assert_eq!(
+            tokens.iter().map(Token::kind).collect::<Vec<_>>(),
+            &[
+                TokenKind::Identifier,
+                TokenKind::RightArrow,
+                TokenKind::Identifier,
+                TokenKind::Minus,
+                TokenKind::Greater,
+                TokenKind::Identifier,
+                TokenKind::RightArrow,
+                TokenKind::Minus,
+                TokenKind::Identifier,
+                TokenKind::EndOfFile
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compound_assignments() {
+        let (tokens, _) = tokenize_text("+= -= *= /= %= + - * / % = +== -=>").unwrap();
+        assert_eq!(
+            tokens.iter().map(Token::kind).collect::<Vec<_>>(),
+            &[
+                TokenKind::PlusAssign,
+                TokenKind::MinusAssign,
+                TokenKind::MulAssign,
+                TokenKind::DivAssign,
+                TokenKind::RemAssign,
+                TokenKind::Plus,
+                TokenKind::Minus,
+                TokenKind::Mul,
+                TokenKind::Div,
+                TokenKind::Rem,
+                TokenKind::Assign,
+                TokenKind::PlusAssign,
+                TokenKind::Assign,
+                TokenKind::MinusAssign,
+                TokenKind::Greater,
+                TokenKind::EndOfFile
+            ]
+        );
+    }
+
+    #[test]
+    fn test_token_map_prefixes() {
+        for (token_chars, _) in OTHER_TOKENS {
+            for l in 0..token_chars.len() {
+                assert!(TOKEN_MAP.contains_key(&token_chars[..l]), "{token_chars:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_comparisons_and_shifts() {
+        let (tokens, _) = tokenize_text("< <= << > >= >> <<= >>> <<<").unwrap();
+        assert_eq!(
+            tokens.iter().map(Token::kind).collect::<Vec<_>>(),
+            &[
+                TokenKind::Less,
+                TokenKind::LessOrEqual,
+                TokenKind::ShiftLeft,
+                TokenKind::Greater,
+                TokenKind::GreaterOrEqual,
+                TokenKind::ShiftRight,
+                TokenKind::ShiftLeft,
+                TokenKind::Assign,
+                TokenKind::ShiftRight,
+                TokenKind::Greater,
+                TokenKind::ShiftLeft,
+                TokenKind::Less,
+                TokenKind::EndOfFile
+            ]
+        );
+    }
+
+    #[test]
+    fn test_not_equal() {
+        let (tokens, _) = tokenize_text("! != == = !x !==").unwrap();
+        assert_eq!(
+            tokens.iter().map(Token::kind).collect::<Vec<_>>(),
+            &[
+                TokenKind::Not,
+                TokenKind::NotEqual,
+                TokenKind::Equal,
+                TokenKind::Assign,
+                TokenKind::Not,
+                TokenKind::Identifier,
+                TokenKind::NotEqual,
+                TokenKind::Assign,
+                TokenKind::EndOfFile
+            ]
+        );
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        let (tokens, _) = tokenize_text("a && b & c || d | e &&& |||").unwrap();
+        assert_eq!(
+            tokens.iter().map(Token::kind).collect::<Vec<_>>(),
+            &[
+                TokenKind::Identifier,
+                TokenKind::LogicalAnd,
+                TokenKind::Identifier,
+                TokenKind::And,
+                TokenKind::Identifier,
+                TokenKind::LogicalOr,
+                TokenKind::Identifier,
+                TokenKind::Or,
+                TokenKind::Identifier,
+                TokenKind::LogicalAnd,
+                TokenKind::And,
+                TokenKind::LogicalOr,
+                TokenKind::Or,
+                TokenKind::EndOfFile
+            ]
+        );
+    }
+
+    #[test]
+    fn test_return_keyword() {
+        let (tokens, literal_data) = tokenize_text("return; returns return_").unwrap();
+        assert_eq!(
+            tokens.iter().map(Token::kind).collect::<Vec<_>>(),
+            &[
+                TokenKind::Return,
+                TokenKind::EndOfStatement,
+                TokenKind::Identifier,
+                TokenKind::Identifier,
+                TokenKind::EndOfFile
+            ]
+        );
+        assert_eq!(
+            literal_data.try_get_identifier(&tokens[2]).unwrap(),
+            "returns"
+        );
+    }
+
+    #[test]
+    fn test_bool_literals() {
+        let (tokens, literal_data) = tokenize_text("true false truthy false_").unwrap();
+        assert_eq!(
+            tokens.iter().map(Token::kind).collect::<Vec<_>>(),
+            &[
+                TokenKind::BoolLiteral,
+                TokenKind::BoolLiteral,
+                TokenKind::Identifier,
+                TokenKind::Identifier,
+                TokenKind::EndOfFile
+            ]
+        );
+        assert_eq!(literal_data.try_get_bool_literal(&tokens[0]), Some(true));
+        assert_eq!(literal_data.try_get_bool_literal(&tokens[1]), Some(false));
+        assert_eq!(
+            literal_data.try_get_identifier(&tokens[2]).unwrap(),
+            "truthy"
+        );
+    }
+
+    #[test]
+    fn test_underscore() {
+        let (tokens, literal_data) = tokenize_text("_ _x => =").unwrap();
+        assert_eq!(
+            tokens.iter().map(Token::kind).collect::<Vec<_>>(),
+            &[
+                TokenKind::Underscore,
+                TokenKind::Identifier,
+                TokenKind::FatArrow,
+                TokenKind::Assign,
+                TokenKind::EndOfFile
+            ]
+        );
+        assert_eq!(literal_data.try_get_identifier(&tokens[1]).unwrap(), "_x");
+    }
+
+    #[test]
+    fn test_token_histogram() {
+        let (tokens, _) = tokenize_text("hello, world!").unwrap();
+        let histogram = token_histogram(&tokens);
+        assert_eq!(histogram[&TokenKind::Identifier], 2);
+        assert_eq!(histogram[&TokenKind::Comma], 1);
+        assert_eq!(histogram[&TokenKind::Not], 1);
+        assert_eq!(histogram[&TokenKind::EndOfFile], 1);
+        assert_eq!(histogram.len(), 4);
+    }
+
+    fn tokenize_suffixed(input: &str) -> (String, Option<String>) {
+        let (tokens, literal_data) = tokenize_text(input).unwrap();
+        assert_eq!(tokens.len(), 2);
+        let text = literal_data.try_get_integer_literal(&tokens[0]).unwrap();
+        let suffix = literal_data.try_get_integer_suffix(&tokens[0]);
+        (text.clone(), suffix.cloned())
+    }
+
+    #[test]
+    fn test_integer_suffixes() {
+        for (input, text, suffix) in [
+            ("123i32", "123", Some("i32")),
+            ("0u8", "0", Some("u8")),
+            ("456usize", "456", Some("usize")),
+            ("1_000i64", "1000", Some("i64")),
+            ("789", "789", None),
+        ] {
+            assert_eq!(
+                tokenize_suffixed(input),
+                (text.to_string(), suffix.map(str::to_string))
+            );
+        }
+
+        for input in ["10foo", "10i128", "1.5u8"] {
+            let error = tokenize_text(input).unwrap_err();
+            assert_eq!(error.kind, TokenizingErrorKind::InvalidSuffix, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_prefixed_literals_with_suffixes() {
+        for (input, text, suffix) in [
+            ("0xFFu8", "0xFF", "u8"),
+            ("0b1010i32", "0b1010", "i32"),
+            ("0o17u16", "0o17", "u16"),
+        ] {
+            assert_eq!(
+                tokenize_suffixed(input),
+                (text.to_string(), Some(suffix.to_string()))
+            );
+        }
+        assert_eq!(tokenize_suffixed("0xff"), ("0xff".to_string(), None));
+
+        for (input, kind, column) in [
+            ("0xu8", TokenizingErrorKind::InvalidDigit, 3),
+            ("0x_u8", TokenizingErrorKind::InvalidSeparator, 3),
+            ("0x1_u8", TokenizingErrorKind::InvalidSeparator, 4),
+            ("0b12u8", TokenizingErrorKind::InvalidDigit, 4),
+            ("0xFFx8", TokenizingErrorKind::InvalidDigit, 5),
+            ("0xFFu7", TokenizingErrorKind::InvalidSuffix, 5),
+        ] {
+            let error = tokenize_text(input).unwrap_err();
+            assert_eq!(error.kind, kind, "{input}");
+            assert_eq!(error.location, Location { line: 1, column });
+        }
+    }
+
+    #[test]
+    fn test_suffix_range() {
+        for input in [
+            "0xFFu8",
+            "0b1000_0000i8",
+            "0o177777u16",
+            "0xFFFF_FFFF_FFFF_FFFFu64",
+        ] {
+            assert!(tokenize_text(input).is_ok(), "{input}");
+        }
+        for input in [
+            "0x100u8",
+            "256u8",
+            "0b1_0000_0001i8",
+            "0x1_0000_0000_0000_0000usize",
+        ] {
+            let error = tokenize_text(input).unwrap_err();
+            assert!(
+                matches!(error.kind, TokenizingErrorKind::IntegerOutOfRange),
+                "{input}"
+            );
+            assert_eq!(error.location, Location { line: 1, column: 1 });
+        }
+    }
+
+    #[test]
+    fn test_describe_token() {
+        let (tokens, literal_data) = tokenize_text("foo\n  + 'c' 255u8").unwrap();
+        let descriptions: Vec<_> = tokens
+            .iter()
+            .map(|token| describe_token(token, &literal_data))
+            .collect();
+        assert_eq!(
+            descriptions,
+            &[
+                "Identifier(\"foo\") @ 1:1",
+                "Plus @ 2:3",
+                "CharLiteral('c') @ 2:5",
+                "IntegerLiteral(\"255u8\") @ 2:9",
+                "EndOfFile @ 2:14",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_byte_to_location() {
+        let source = "ab\n\täx\n";
+        let location = |offset| byte_to_location(source, offset);
+        assert_eq!(location(0), Location { line: 1, column: 1 });
+        assert_eq!(location(1), Location { line: 1, column: 2 });
+        // the newline itself is at the end of its line
+        assert_eq!(location(2), Location { line: 1, column: 3 });
+        assert_eq!(location(3), Location { line: 2, column: 1 });
+        assert_eq!(location(4), Location { line: 2, column: 2 });
+        // the second byte of `ä`
+        assert_eq!(location(5), Location { line: 2, column: 2 });
+        assert_eq!(location(6), Location { line: 2, column: 3 });
+        assert_eq!(location(8), Location { line: 3, column: 1 });
+        assert_eq!(location(100), Location { line: 3, column: 1 });
+
+        let (tokens, _) = tokenize_text(source).unwrap();
+        assert_eq!(tokens.last().unwrap().location(), location(source.len()));
+    }
+
+    #[test]
+    fn test_long_operator_run() {
+        let (tokens, _) = tokenize_text(":::::::::::").unwrap();
+        let kinds: Vec<_> = tokens.iter().map(Token::kind).collect();
+        assert_eq!(kinds[..5], [TokenKind::PathSep; 5]);
+        assert_eq!(
+            kinds[5..],
+            [TokenKind::FieldTypeSeparator, TokenKind::EndOfFile]
+        );
+
+        for run in [
+            ":".repeat(200_000),
+            "-".repeat(200_000),
+            "=>".repeat(100_000),
+        ] {
+            let (tokens, _) = tokenize_text(&run).unwrap();
+            assert!(tokens.len() >= 100_000);
+        }
+    }
+
+    extern crate test;
+
+    // quadratic work in operator runs shows up here
+    #[bench]
+    fn bench_long_operator_run(b: &mut test::Bencher) {
+        let input = ":".repeat(200_000);
+        b.iter(|| tokenize_text(&input).unwrap());
+    }
+
+    fn literal_heavy_input() -> String {
+        (0..2000)
+            .map(|i| format!("x{i} := {i} + {i}.5 * \"s{i}\";\n"))
+            .collect()
+    }
+
+    #[bench]
+    fn bench_literal_heavy(b: &mut test::Bencher) {
+        let input = literal_heavy_input();
+        b.iter(|| tokenize_text(&input).unwrap());
+    }
+
+    /// Fills a map keyed by the locations of the tokens of a literal-heavy
+    /// input and looks each of them up, like `LiteralData` does.
+    fn bench_location_keys<S: BuildHasher + Default>(b: &mut test::Bencher) {
+        let (tokens, _) = tokenize_text(&literal_heavy_input()).unwrap();
+        b.iter(|| {
+            let mut map: HashMap<Location, usize, S> = HashMap::default();
+            for (i, token) in tokens.iter().enumerate() {
+                map.insert(token.location(), i);
+            }
+            tokens
+                .iter()
+                .filter(|token| map.contains_key(&token.location()))
+                .count()
+        });
+    }
+
+    #[bench]
+    fn bench_location_map(b: &mut test::Bencher) {
+        bench_location_keys::<BuildHasherDefault<LocationHasher>>(b);
+    }
+
+    // the baseline for `bench_location_map`, with the default hasher
+    #[bench]
+    fn bench_location_map_random_state(b: &mut test::Bencher) {
+        bench_location_keys::<RandomState>(b);
+    }
+
+    #[test]
+    fn test_integer_values() {
+        for (input, value) in [
+            ("1_000", Ok(1000)),
+            ("0xFF", Ok(255)),
+            ("0b1010", Ok(10)),
+            ("0o17", Ok(15)),
+            ("18446744073709551615", Ok(u64::MAX)),
+            ("18446744073709551616", Err(IntegerParseError::Overflow)),
+            ("0x1_0000_0000_0000_0000", Err(IntegerParseError::Overflow)),
+        ] {
+            let (tokens, literal_data) = tokenize_text(input).unwrap();
+            assert_eq!(
+                literal_data.try_get_integer_value(&tokens[0]),
+                Some(value),
+                "{input}"
+            );
+        }
+
+        let (tokens, literal_data) = tokenize_text("x").unwrap();
+        assert_eq!(literal_data.try_get_integer_value(&tokens[0]), None);
+
+        assert_eq!(integer_value("1_0"), Ok(10));
+        assert_eq!(integer_value(""), Err(IntegerParseError::Empty));
+        assert_eq!(integer_value("0x"), Err(IntegerParseError::Empty));
+        assert_eq!(integer_value("12a"), Err(IntegerParseError::InvalidDigit));
+    }
+
+    #[test]
+    fn test_dense_operators() {
+        let (tokens, _) = tokenize_text(":=<<=>=&&").unwrap();
+        let kinds: Vec<_> = tokens.iter().map(Token::kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::DefineVar,
+                TokenKind::ShiftLeft,
+                TokenKind::FatArrow,
+                TokenKind::Assign,
+                TokenKind::LogicalAnd,
+                TokenKind::EndOfFile,
+            ]
+        );
+        let columns: Vec<_> = tokens.iter().map(|t| t.location().column).collect();
+        assert_eq!(columns, vec![1, 3, 5, 7, 8, 10]);
+
+        // the scanner is left right after the token, whatever it stopped at
+        let (tokens, _) = tokenize_text("-\n>!").unwrap();
+        let kinds: Vec<_> = tokens.iter().map(Token::kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Minus,
+                TokenKind::Greater,
+                TokenKind::Not,
+                TokenKind::EndOfFile
+            ]
+        );
+        assert_eq!(tokens[1].location(), Location { line: 2, column: 1 });
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let (tokens, _) = tokenize_text("x := 'a' + 1;").unwrap();
+        let json = serde_json::to_string(&tokens).unwrap();
+        let deserialized: Vec<Token> = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, tokens);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_tokens_to_json() {
+        let (tokens, literal_data) = tokenize_text("f(255u8, \"hi\")").unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&tokens_to_json(&tokens, &literal_data)).unwrap();
+        let texts: Vec<_> = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|token| token.get("text").and_then(serde_json::Value::as_str))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![Some("f"), None, Some("255u8"), None, Some("hi"), None, None]
+        );
+        assert_eq!(json[0]["kind"], "Identifier");
+        assert_eq!(json[0]["span"]["end"]["column"], 2);
+    }
+
+    #[test]
+    fn test_garbage_input() {
+        let output = tokenize_text_recovering("@$`~", &TokenizerOptions::default());
+        let columns: Vec<_> = output
+            .errors
+            .iter()
+            .map(|error| {
+                assert_eq!(error.kind, TokenizingErrorKind::UnknownToken);
+                error.location.column
+            })
+            .collect();
+        assert_eq!(columns, vec![1, 2, 3, 4]);
+        assert_eq!(
+            output.tokens.iter().map(Token::kind).collect::<Vec<_>>(),
+            vec![TokenKind::EndOfFile]
+        );
+
+        // every char on its own and after every other char, which covers all
+        // the ways a token can start and end early
+        let chars: Vec<char> = (0..128u8)
+            .map(char::from)
+            .chain(['€', '\u{306}', '\u{200b}', '\u{feff}', '\u{10ffff}'])
+            .collect();
+        for &a in &chars {
+            for &b in &chars {
+                let input = format!("{a}{b}");
+                let output = tokenize_text_recovering(&input, &TokenizerOptions::default());
+                assert!(output.errors.len() <= 2, "{input:?}");
+                assert_eq!(
+                    output.tokens.last().map(Token::kind),
+                    Some(TokenKind::EndOfFile)
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,18 @@
+use lang_test::{compile, tokenize_text, Program, TokenKind};
+
+#[test]
+fn tokenize_and_parse_function() {
+    let source = "fn add(a: Int, b: Int) -> Int { a + b }";
+    let (tokens, literal_data) = tokenize_text(source).unwrap();
+    assert_eq!(tokens[0].kind(), TokenKind::FunctionDefinition);
+    assert_eq!(literal_data.try_get_identifier(&tokens[1]).unwrap(), "add");
+
+    assert!(Program::from_tokens(tokens, literal_data).is_ok());
+}
+
+#[test]
+fn compile_reports_diagnostics() {
+    let diagnostics = compile("fn f() { x = !; }").unwrap_err();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, "E0104");
+}